@@ -0,0 +1,438 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    meta::ParseNestedMeta, parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Expr,
+    ExprLit, ExprPath, Fields, FieldsNamed, Lit, LitStr, Meta, Token,
+};
+
+/// Derive `avro_poc::AvroSchema`, generating a schema matching the serde serialization of the
+/// type.
+///
+/// Structs are mapped to records, enums with only unit variants to Avro enums, and other enums
+/// to unions whose branches are selected by variant name: unit variants are gathered in an enum
+/// branch, newtype variants use the schema of their content, which must then be a named type or
+/// a primitive type named after the variant (e.g. `Long(i64)`), and struct variants a record
+/// named after the variant. Such unions are repeated wherever the enum is used, so the enum cannot
+/// be recursive. Serde `rename`, `rename_all`, `skip`/`skip_serializing` and `default`
+/// attributes are honoured; the namespace can be set with `#[avro(namespace = "...")]`.
+#[proc_macro_derive(AvroSchema, attributes(avro))]
+pub fn derive_avro_schema(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+enum DefaultAttr {
+    Trait,
+    Path(ExprPath),
+}
+
+#[derive(Default)]
+struct Attrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    skip: bool,
+    default: Option<DefaultAttr>,
+    namespace: Option<String>,
+    doc: Option<String>,
+}
+
+fn parse_attrs(attrs: &[Attribute]) -> syn::Result<Attrs> {
+    let mut parsed = Attrs::default();
+    let mut doc = Vec::new();
+    for attr in attrs {
+        if attr.path().is_ident("doc") {
+            if let Meta::NameValue(meta) = &attr.meta {
+                if let Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) = &meta.value
+                {
+                    doc.push(s.value().trim().to_string());
+                }
+            }
+        } else if attr.path().is_ident("avro") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("namespace") {
+                    parsed.namespace = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported avro attribute"))
+                }
+            })?;
+        } else if attr.path().is_ident("serde") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    parsed.rename = serialize_name(&meta)?.or(parsed.rename.take());
+                } else if meta.path.is_ident("rename_all") {
+                    parsed.rename_all = serialize_name(&meta)?.or(parsed.rename_all.take());
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                    parsed.skip = true;
+                } else if meta.path.is_ident("default") {
+                    parsed.default = Some(if meta.input.peek(Token![=]) {
+                        DefaultAttr::Path(meta.value()?.parse::<LitStr>()?.parse()?)
+                    } else {
+                        DefaultAttr::Trait
+                    });
+                } else if meta.path.is_ident("flatten") {
+                    return Err(meta.error("flatten is not supported by AvroSchema"));
+                } else if meta.path.is_ident("tag")
+                    || meta.path.is_ident("content")
+                    || meta.path.is_ident("untagged")
+                {
+                    return Err(meta.error("only externally tagged enums are supported"));
+                } else {
+                    skip_meta(&meta)?;
+                }
+                Ok(())
+            })?;
+        }
+    }
+    if !doc.is_empty() {
+        parsed.doc = Some(doc.join("\n"));
+    }
+    Ok(parsed)
+}
+
+/// Parse `name = "..."` or `name(serialize = "...")`.
+fn serialize_name(meta: &ParseNestedMeta) -> syn::Result<Option<String>> {
+    if meta.input.peek(Token![=]) {
+        return Ok(Some(meta.value()?.parse::<LitStr>()?.value()));
+    }
+    let mut name = None;
+    meta.parse_nested_meta(|meta| {
+        if meta.path.is_ident("serialize") {
+            name = Some(meta.value()?.parse::<LitStr>()?.value());
+        } else {
+            skip_meta(&meta)?;
+        }
+        Ok(())
+    })?;
+    Ok(name)
+}
+
+fn skip_meta(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|meta| skip_meta(&meta))?;
+    }
+    Ok(())
+}
+
+/// Apply serde `rename_all` rule to a field (snake_case) or a variant (PascalCase) name.
+fn rename_all(rule: &str, name: &str, variant: bool) -> Option<String> {
+    let snake = if variant {
+        let mut snake = String::new();
+        for (i, c) in name.char_indices() {
+            if i > 0 && c.is_uppercase() {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        }
+        snake
+    } else {
+        name.to_string()
+    };
+    let pascal = || {
+        snake
+            .split('_')
+            .map(|word| {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            })
+            .collect::<String>()
+    };
+    Some(match rule {
+        "lowercase" if variant => name.to_ascii_lowercase(),
+        "UPPERCASE" if variant => name.to_ascii_uppercase(),
+        "lowercase" => snake,
+        "UPPERCASE" => snake.to_ascii_uppercase(),
+        "PascalCase" if variant => name.to_string(),
+        "PascalCase" => pascal(),
+        "camelCase" => {
+            let pascal = if variant { name.to_string() } else { pascal() };
+            let mut chars = pascal.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_lowercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        }
+        "snake_case" => snake,
+        "SCREAMING_SNAKE_CASE" => snake.to_ascii_uppercase(),
+        "kebab-case" => snake.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => snake.replace('_', "-").to_ascii_uppercase(),
+        _ => return None,
+    })
+}
+
+fn renamed(
+    attrs: &Attrs,
+    ident: &syn::Ident,
+    rule: Option<&str>,
+    variant: bool,
+) -> syn::Result<String> {
+    if let Some(rename) = &attrs.rename {
+        return Ok(rename.clone());
+    }
+    let name = ident.to_string();
+    let name = name.strip_prefix("r#").unwrap_or(&name);
+    match rule {
+        Some(rule) => rename_all(rule, name, variant)
+            .ok_or_else(|| syn::Error::new(ident.span(), format!("unknown rename rule {rule}"))),
+        None => Ok(name.to_string()),
+    }
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream> {
+    let attrs = parse_attrs(&input.attrs)?;
+    let name = renamed(&attrs, &input.ident, None, false)?;
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => {
+                let container_default = attrs.default.as_ref().map(|default| match default {
+                    DefaultAttr::Trait => quote!(<Self as ::core::default::Default>::default()),
+                    DefaultAttr::Path(path) => quote!(#path()),
+                });
+                let fields = record_fields(fields, attrs.rename_all.as_deref(), container_default)?;
+                named_type(&name, &attrs, record(&name, &attrs, fields))
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                quote!(<#ty as ::avro_poc::AvroSchema>::json_schema(defined))
+            }
+            Fields::Unit => quote!(::avro_poc::__private::serde_json::Value::String(
+                "null".into()
+            )),
+            Fields::Unnamed(fields) => {
+                return Err(syn::Error::new_spanned(
+                    fields,
+                    "tuple structs are not supported by AvroSchema",
+                ))
+            }
+        },
+        Data::Enum(data) => {
+            let mut symbols = Vec::new();
+            let mut branches = Vec::new();
+            for variant in &data.variants {
+                let variant_attrs = parse_attrs(&variant.attrs)?;
+                if variant_attrs.skip {
+                    continue;
+                }
+                let variant_name = renamed(
+                    &variant_attrs,
+                    &variant.ident,
+                    attrs.rename_all.as_deref(),
+                    true,
+                )?;
+                match &variant.fields {
+                    Fields::Unit => symbols.push(variant_name),
+                    Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                        let ty = &fields.unnamed[0].ty;
+                        branches
+                            .push(quote!(<#ty as ::avro_poc::AvroSchema>::json_schema(defined)));
+                    }
+                    Fields::Named(fields) => {
+                        let fields =
+                            record_fields(fields, variant_attrs.rename_all.as_deref(), None)?;
+                        let record_attrs = Attrs {
+                            namespace: attrs.namespace.clone(),
+                            doc: variant_attrs.doc,
+                            ..Default::default()
+                        };
+                        let record = record(&variant_name, &record_attrs, fields);
+                        branches.push(named_type(&variant_name, &record_attrs, record));
+                    }
+                    Fields::Unnamed(fields) => {
+                        return Err(syn::Error::new_spanned(
+                            fields,
+                            "tuple variants are not supported by AvroSchema",
+                        ))
+                    }
+                }
+            }
+            let enum_schema = enumeration(&name, &attrs, &symbols);
+            if branches.is_empty() {
+                named_type(&name, &attrs, enum_schema)
+            } else {
+                let fullname = fullname(&name, &attrs);
+                let units = (!symbols.is_empty()).then(|| {
+                    let enum_schema = named_type(&name, &attrs, enum_schema);
+                    quote!(branches.push(#enum_schema);)
+                });
+                // Unions have no name to be referenced by, so they are expanded wherever the enum
+                // is used, the enum being marked meanwhile to detect recursion.
+                quote! {
+                    let expanding = ::std::format!(
+                        "{}:{}",
+                        ::avro_poc::__private::RECURSIVE_UNION,
+                        #fullname,
+                    );
+                    if !defined.insert(expanding.clone()) {
+                        return ::avro_poc::__private::serde_json::json!({
+                            "type": ::avro_poc::__private::RECURSIVE_UNION,
+                            "name": #fullname,
+                        });
+                    }
+                    let mut branches = ::std::vec::Vec::new();
+                    #units
+                    #(branches.push(#branches);)*
+                    defined.remove(&expanding);
+                    ::avro_poc::__private::serde_json::Value::Array(branches)
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "unions are not supported by AvroSchema",
+            ))
+        }
+    };
+    let type_params = input
+        .generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+    let where_clause = input.generics.make_where_clause();
+    for param in type_params {
+        where_clause
+            .predicates
+            .push(parse_quote!(#param: ::avro_poc::AvroSchema));
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::avro_poc::AvroSchema for #ident #ty_generics #where_clause {
+            fn json_schema(
+                defined: &mut ::std::collections::HashSet<::std::string::String>,
+            ) -> ::avro_poc::__private::serde_json::Value {
+                #body
+            }
+        }
+    })
+}
+
+fn fullname(name: &str, attrs: &Attrs) -> String {
+    match &attrs.namespace {
+        Some(namespace) => format!("{namespace}.{name}"),
+        None => name.to_string(),
+    }
+}
+
+/// Named types are only defined once, then referenced by their name.
+fn named_type(name: &str, attrs: &Attrs, definition: TokenStream) -> TokenStream {
+    let fullname = fullname(name, attrs);
+    quote! {
+        if defined.insert(#fullname.into()) {
+            #definition
+        } else {
+            ::avro_poc::__private::serde_json::Value::String(#fullname.into())
+        }
+    }
+}
+
+fn object(r#type: &str, name: &str, attrs: &Attrs, content: TokenStream) -> TokenStream {
+    let namespace = attrs
+        .namespace
+        .as_ref()
+        .map(|ns| quote!(schema.insert("namespace".into(), #ns.into());));
+    let doc = attrs
+        .doc
+        .as_ref()
+        .map(|doc| quote!(schema.insert("doc".into(), #doc.into());));
+    quote! {{
+        let mut schema = ::avro_poc::__private::serde_json::Map::new();
+        schema.insert("type".into(), #r#type.into());
+        schema.insert("name".into(), #name.into());
+        #namespace
+        #doc
+        #content
+        ::avro_poc::__private::serde_json::Value::Object(schema)
+    }}
+}
+
+fn record(name: &str, attrs: &Attrs, fields: TokenStream) -> TokenStream {
+    object(
+        "record",
+        name,
+        attrs,
+        quote! {
+            let mut fields = ::std::vec::Vec::new();
+            #fields
+            schema.insert(
+                "fields".into(),
+                ::avro_poc::__private::serde_json::Value::Array(fields),
+            );
+        },
+    )
+}
+
+fn enumeration(name: &str, attrs: &Attrs, symbols: &[String]) -> TokenStream {
+    object(
+        "enum",
+        name,
+        attrs,
+        quote! {
+            schema.insert(
+                "symbols".into(),
+                ::avro_poc::__private::serde_json::json!([#(#symbols),*]),
+            );
+        },
+    )
+}
+
+fn record_fields(
+    fields: &FieldsNamed,
+    rule: Option<&str>,
+    container_default: Option<TokenStream>,
+) -> syn::Result<TokenStream> {
+    let mut stmts = Vec::new();
+    if let Some(default) = &container_default {
+        stmts.push(quote! {
+            let container_default = ::avro_poc::__private::serde_json::to_value(#default)
+                .expect("default value must be serializable to JSON");
+        });
+    }
+    for field in &fields.named {
+        let attrs = parse_attrs(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let name = renamed(&attrs, field.ident.as_ref().unwrap(), rule, false)?;
+        let ty = &field.ty;
+        let default = match (&attrs.default, &container_default) {
+            (Some(DefaultAttr::Trait), _) => {
+                Some(quote!(<#ty as ::core::default::Default>::default()))
+            }
+            (Some(DefaultAttr::Path(path)), _) => Some(quote!(#path())),
+            (None, Some(_)) => Some(quote!(&container_default[#name])),
+            (None, None) => None,
+        }
+        .map(|default| {
+            quote! {
+                field.insert(
+                    "default".into(),
+                    ::avro_poc::__private::serde_json::to_value(#default)
+                        .expect("default value must be serializable to JSON"),
+                );
+            }
+        });
+        let doc = attrs
+            .doc
+            .as_ref()
+            .map(|doc| quote!(field.insert("doc".into(), #doc.into());));
+        stmts.push(quote! {{
+            let mut field = ::avro_poc::__private::serde_json::Map::new();
+            field.insert("name".into(), #name.into());
+            field.insert("type".into(), <#ty as ::avro_poc::AvroSchema>::json_schema(defined));
+            #doc
+            #default
+            fields.push(::avro_poc::__private::serde_json::Value::Object(field));
+        }});
+    }
+    Ok(quote!(#(#stmts)*))
+}
@@ -1,5 +1,5 @@
 use apache_avro::{to_avro_datum, to_value, Schema};
-use avro_poc::Serializer;
+use avro_poc::{Batch, Serializer};
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use serde::Serialize;
 
@@ -53,6 +53,43 @@ const SCHEMA_COMPLEX: &str = r#"{
     ]
 }"#;
 
+const SCHEMA_NULLABLE: &str = r#"{
+    "type" : "record",
+    "name" : "Nullable",
+    "fields" : [
+        {"name" : "a", "type" : ["null", "int"]},
+        {"name" : "b", "type" : ["null", "long"]},
+        {"name" : "c", "type" : ["null", "string"]},
+        {"name" : "d", "type" : ["string", "null"]},
+        {"name" : "e", "type" : ["null", "double"]},
+        {"name" : "f", "type" : ["null", "boolean"]},
+        {"name" : "g", "type" : ["null", {"type": "array", "items": ["null", "int"]}]},
+        {"name" : "h", "type" : ["null", "int", "string"]}
+    ]
+}"#;
+
+const SCHEMA_ENUM: &str = r#"{
+    "type" : "record",
+    "name" : "Card",
+    "fields" : [
+        {
+            "name" : "suit",
+            "type" : {"type": "enum", "name": "Suit", "symbols": ["Spades", "Hearts", "Diamonds", "Clubs"]}
+        },
+        {
+            "name" : "colors",
+            "type" : {
+                "type": "array",
+                "items": {
+                    "type": "enum",
+                    "name": "Color",
+                    "symbols": ["Red", "Green", "Blue", "Cyan", "Magenta", "Yellow", "Black", "White"]
+                }
+            }
+        }
+    ]
+}"#;
+
 fn bench(c: &mut Criterion, category: &'static str, schema: &'static str, value: impl Serialize) {
     let schema = Schema::parse_str(schema).unwrap();
     let serializer = Serializer::new(&schema).unwrap();
@@ -64,7 +101,7 @@ fn bench(c: &mut Criterion, category: &'static str, schema: &'static str, value:
         BenchmarkId::new(category, "apache_avro"),
         &schema,
         |b, schema| {
-            b.iter(|| to_avro_datum(&schema, to_value(black_box(&value)).unwrap()).unwrap());
+            b.iter(|| to_avro_datum(schema, to_value(black_box(&value)).unwrap()).unwrap());
         },
     );
     c.bench_with_input(
@@ -74,6 +111,34 @@ fn bench(c: &mut Criterion, category: &'static str, schema: &'static str, value:
             b.iter(|| serializer.serialize(black_box(&value)).unwrap());
         },
     );
+    let apache_value = to_value(&value).unwrap();
+    c.bench_with_input(
+        BenchmarkId::new(category, "avro_poc_apache_value"),
+        &serializer,
+        |b, serializer| {
+            b.iter(|| {
+                serializer
+                    .serialize_apache_value(black_box(&apache_value))
+                    .unwrap()
+            });
+        },
+    );
+    let bytes = serializer.serialize(&value).unwrap();
+    let dynamic = serializer.decode(&bytes).unwrap();
+    c.bench_with_input(
+        BenchmarkId::new(category, "avro_poc_value"),
+        &serializer,
+        |b, serializer| {
+            b.iter(|| serializer.serialize(black_box(&dynamic)).unwrap());
+        },
+    );
+    c.bench_with_input(
+        BenchmarkId::new(category, "avro_poc_decode"),
+        &serializer,
+        |b, serializer| {
+            b.iter(|| serializer.decode(black_box(&bytes)).unwrap());
+        },
+    );
 }
 
 fn int(c: &mut Criterion) {
@@ -127,6 +192,17 @@ fn recursive(c: &mut Criterion) {
         ],
     };
     bench(c, "recursive", SCHEMA_RECURSIVE, value);
+    // Complete ternary tree of 1093 nodes, to measure the node lookups of each level.
+    fn tree(value: i32, depth: usize) -> Tree {
+        Tree {
+            value,
+            children: match depth {
+                0 => vec![],
+                _ => (0..3).map(|i| tree(value * 3 + i, depth - 1)).collect(),
+            },
+        }
+    }
+    bench(c, "recursive_deep", SCHEMA_RECURSIVE, tree(0, 6));
 }
 
 fn complex(c: &mut Criterion) {
@@ -158,7 +234,212 @@ fn complex(c: &mut Criterion) {
         }),
     };
     bench(c, "complex", SCHEMA_COMPLEX, value);
+    // Every optional field set, selecting the non-null branch of each union.
+    let value = UserInfo {
+        username: "wyfo".into(),
+        age: Some(29),
+        phone: Some("0123456789".into()),
+        housenum: Some("42".into()),
+        address: Some(Address {
+            street: Some("rue de la Gare".into()),
+            city: Some("Chaville".into()),
+            zip: Some("92370".into()),
+            country: "France".into(),
+        }),
+    };
+    bench(c, "complex_full", SCHEMA_COMPLEX, value);
+}
+
+fn nullable(c: &mut Criterion) {
+    #[derive(serde::Serialize)]
+    struct Nullable {
+        a: Option<i32>,
+        b: Option<i64>,
+        c: Option<String>,
+        d: Option<String>,
+        e: Option<f64>,
+        f: Option<bool>,
+        g: Option<Vec<Option<i32>>>,
+        h: Option<i32>,
+    }
+    let value = Nullable {
+        a: Some(42),
+        b: None,
+        c: Some("foo".into()),
+        d: None,
+        e: Some(0.5),
+        f: None,
+        g: Some(vec![Some(1), None, Some(2), None]),
+        h: Some(0),
+    };
+    bench(c, "nullable", SCHEMA_NULLABLE, value);
+}
+
+fn enumeration(c: &mut Criterion) {
+    #[derive(serde::Serialize)]
+    #[allow(dead_code)]
+    enum Suit {
+        Spades,
+        Hearts,
+        Diamonds,
+        Clubs,
+    }
+    #[derive(serde::Serialize)]
+    #[allow(dead_code)]
+    enum Color {
+        Red,
+        Green,
+        Blue,
+        Cyan,
+        Magenta,
+        Yellow,
+        Black,
+        White,
+    }
+    #[derive(serde::Serialize)]
+    struct Card {
+        suit: Suit,
+        colors: Vec<Color>,
+    }
+    let value = Card {
+        suit: Suit::Diamonds,
+        colors: vec![Color::Red, Color::White, Color::Magenta, Color::Blue],
+    };
+    bench(c, "enum", SCHEMA_ENUM, value);
+}
+
+fn batch(c: &mut Criterion) {
+    #[derive(serde::Serialize)]
+    struct Foo {
+        bar: String,
+        baz: Option<i32>,
+    }
+    let values: Vec<_> = (0..1000)
+        .map(|i| Foo {
+            bar: format!("bar{i}"),
+            baz: (i % 2 == 0).then_some(i),
+        })
+        .collect();
+    let schema = Schema::parse_str(SCHEMA_RECORD).unwrap();
+    let serializer = Serializer::new(&schema).unwrap();
+    let batch = serializer.serialize_batch(&values).unwrap();
+    for (value, bytes) in values.iter().zip(batch.iter()) {
+        assert_eq!(serializer.serialize(value).unwrap(), bytes);
+    }
+    c.bench_with_input(
+        BenchmarkId::new("batch", "avro_poc"),
+        &serializer,
+        |b, serializer| {
+            b.iter(|| {
+                black_box(&values)
+                    .iter()
+                    .map(|value| serializer.serialize(value).unwrap())
+                    .collect::<Vec<_>>()
+            });
+        },
+    );
+    c.bench_with_input(
+        BenchmarkId::new("batch", "avro_poc_batch"),
+        &serializer,
+        |b, serializer| {
+            b.iter(|| serializer.serialize_batch(black_box(&values)).unwrap());
+        },
+    );
+    let mut batch = Batch::new();
+    c.bench_with_input(
+        BenchmarkId::new("batch", "avro_poc_batch_reuse"),
+        &serializer,
+        |b, serializer| {
+            b.iter(|| {
+                batch.clear();
+                serializer
+                    .serialize_batch_into(black_box(&values), &mut batch)
+                    .unwrap();
+            });
+        },
+    );
+}
+
+fn writer(c: &mut Criterion) {
+    #[derive(serde::Serialize)]
+    struct Foo {
+        bar: String,
+        baz: Option<i32>,
+    }
+    let values: Vec<_> = (0..100)
+        .map(|i| Foo {
+            bar: format!("bar{i}"),
+            baz: (i % 2 == 0).then_some(i),
+        })
+        .collect();
+    let schema = Schema::parse_str(SCHEMA_RECORD).unwrap();
+    let serializer = Serializer::new(&schema).unwrap();
+    let mut buf = Vec::new();
+    c.bench_with_input(
+        BenchmarkId::new("writer", "vec"),
+        &serializer,
+        |b, serializer| {
+            b.iter(|| {
+                buf.clear();
+                for value in black_box(&values) {
+                    serializer.write(value, &mut buf).unwrap();
+                }
+            });
+        },
+    );
+    c.bench_with_input(
+        BenchmarkId::new("writer", "serialize_into"),
+        &serializer,
+        |b, serializer| {
+            b.iter(|| {
+                buf.clear();
+                for value in black_box(&values) {
+                    serializer.serialize_into(value, &mut buf).unwrap();
+                }
+            });
+        },
+    );
+    #[cfg(feature = "bytes")]
+    {
+        let mut buf = bytes::BytesMut::new();
+        c.bench_with_input(
+            BenchmarkId::new("writer", "serialize_into_buf"),
+            &serializer,
+            |b, serializer| {
+                b.iter(|| {
+                    buf.clear();
+                    for value in black_box(&values) {
+                        serializer.serialize_into_buf(value, &mut buf).unwrap();
+                    }
+                });
+            },
+        );
+    }
+    // Unbuffered writer, making a system call for each write.
+    let file = std::fs::File::create("/dev/null").unwrap();
+    c.bench_with_input(
+        BenchmarkId::new("writer", "file"),
+        &serializer,
+        |b, serializer| {
+            b.iter(|| {
+                for value in black_box(&values) {
+                    serializer.write(value, &file).unwrap();
+                }
+            });
+        },
+    );
 }
 
-criterion_group!(benches, int, string_array, simple, recursive, complex);
+criterion_group!(
+    benches,
+    int,
+    string_array,
+    simple,
+    recursive,
+    complex,
+    nullable,
+    enumeration,
+    batch,
+    writer
+);
 criterion_main!(benches);
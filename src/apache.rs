@@ -0,0 +1,267 @@
+use std::io::{self, Write};
+
+use apache_avro::types::Value;
+use integer_encoding::VarIntWriter;
+
+use crate::{
+    error::{PathSegment, SerializationError},
+    logical,
+    node::{Field, Node, NodeId},
+    schema::SerializationSchemaKind,
+    ser::WithSegment,
+    transcode::JsonEncoder,
+};
+
+fn kind(value: &Value) -> SerializationSchemaKind {
+    match value {
+        Value::Null => SerializationSchemaKind::Null,
+        Value::Boolean(_) => SerializationSchemaKind::Boolean,
+        Value::Int(_) => SerializationSchemaKind::Int,
+        Value::Long(_) => SerializationSchemaKind::Long,
+        Value::Float(_) => SerializationSchemaKind::Float,
+        Value::Double(_) => SerializationSchemaKind::Double,
+        Value::Bytes(_) => SerializationSchemaKind::Bytes,
+        Value::String(_) => SerializationSchemaKind::String,
+        Value::Fixed(..) => SerializationSchemaKind::Fixed,
+        Value::Enum(..) => SerializationSchemaKind::Enum,
+        Value::Union(..) => SerializationSchemaKind::Union,
+        Value::Array(_) => SerializationSchemaKind::Array,
+        Value::Map(_) => SerializationSchemaKind::Map,
+        Value::Record(_) => SerializationSchemaKind::Record,
+        Value::Date(_) => SerializationSchemaKind::Date,
+        Value::Decimal(_) => SerializationSchemaKind::Decimal,
+        Value::TimeMillis(_) => SerializationSchemaKind::TimeMillis,
+        Value::TimeMicros(_) => SerializationSchemaKind::TimeMicros,
+        Value::TimestampMillis(_) => SerializationSchemaKind::TimestampMillis,
+        Value::TimestampMicros(_) => SerializationSchemaKind::TimestampMicros,
+        Value::Duration(_) => SerializationSchemaKind::Duration,
+        Value::Uuid(_) => SerializationSchemaKind::Uuid,
+        // Variants added by newer versions of apache_avro.
+        #[allow(unreachable_patterns)]
+        _ => SerializationSchemaKind::Null,
+    }
+}
+
+/// Whether the value can be encoded with the schema, without looking into nested values.
+fn matches(value: &Value, schema: &Node) -> bool {
+    matches!(
+        (schema, value),
+        (Node::Null, Value::Null)
+            | (Node::Boolean, Value::Boolean(_))
+            | (
+                Node::Int,
+                Value::Int(_) | Value::Date(_) | Value::TimeMillis(_)
+            )
+            | (
+                Node::Long,
+                Value::Int(_)
+                    | Value::Long(_)
+                    | Value::TimeMicros(_)
+                    | Value::TimestampMillis(_)
+                    | Value::TimestampMicros(_)
+            )
+            | (Node::Float, Value::Float(_))
+            | (Node::Double, Value::Float(_) | Value::Double(_))
+            | (Node::Bytes, Value::Bytes(_))
+            | (Node::String, Value::String(_))
+            | (Node::Fixed { .. }, Value::Fixed(..))
+            | (Node::Enum { .. }, Value::Enum(..))
+            | (Node::Array(_), Value::Array(_))
+            | (Node::Map(_), Value::Map(_))
+            | (Node::Record { .. }, Value::Record(_))
+            | (Node::Decimal { .. }, Value::Decimal(_))
+            | (Node::Uuid, Value::Uuid(_) | Value::String(_))
+            | (Node::Date, Value::Date(_) | Value::Int(_))
+            | (Node::TimeMillis, Value::TimeMillis(_) | Value::Int(_))
+            | (Node::TimeMicros, Value::TimeMicros(_) | Value::Long(_))
+            | (
+                Node::TimestampMillis,
+                Value::TimestampMillis(_) | Value::Long(_)
+            )
+            | (
+                Node::TimestampMicros,
+                Value::TimestampMicros(_) | Value::Long(_)
+            )
+            | (Node::Duration { .. }, Value::Duration(_))
+    )
+}
+
+/// Encodes [`apache_avro::types::Value`] through the compiled schema.
+pub(crate) struct ApacheEncoder<'a, W> {
+    pub(crate) writer: W,
+    pub(crate) nodes: &'a [Node],
+}
+
+impl<'a, W> ApacheEncoder<'a, W>
+where
+    W: io::Write,
+{
+    fn node(&self, id: NodeId) -> &'a Node {
+        &self.nodes[id as usize]
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SerializationError> {
+        self.writer.write_varint(bytes.len() as i64)?;
+        self.writer.write_all(bytes)?;
+        Ok(())
+    }
+
+    fn write_fixed(&mut self, bytes: &[u8], size: usize) -> Result<(), SerializationError> {
+        if bytes.len() != size {
+            return Err(format!("expected fixed {size}, found {}", bytes.len()).into());
+        }
+        self.writer.write_all(bytes)?;
+        Ok(())
+    }
+
+    /// Record fields are matched by name, missing ones taking their default.
+    fn write_record(
+        &mut self,
+        fields: &'a [Field],
+        entries: &[(String, Value)],
+    ) -> Result<(), SerializationError> {
+        for (i, field) in fields.iter().enumerate() {
+            // Entries are usually in field order.
+            let entry = match entries.get(i) {
+                Some((name, value)) if *name == field.name => Some(value),
+                _ => entries
+                    .iter()
+                    .find_map(|(name, value)| (*name == field.name).then_some(value)),
+            };
+            match (entry, &field.default) {
+                (Some(value), _) => self.encode(value, self.node(field.node)),
+                (None, Some(default)) => JsonEncoder {
+                    writer: &mut self.writer,
+                    nodes: self.nodes,
+                }
+                .encode(default, &self.nodes[field.node as usize], true),
+                (None, None) => return Err(format!("missing field {}", field.name).into()),
+            }
+            .with_segment(|| PathSegment::Key(field.name.clone()))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn encode(
+        &mut self,
+        value: &Value,
+        schema: &'a Node,
+    ) -> Result<(), SerializationError> {
+        match (schema, value) {
+            (
+                Node::Union { branches, .. } | Node::Nullable { branches, .. },
+                Value::Union(index, value),
+            ) => {
+                let branch = branches
+                    .get(*index as usize)
+                    .ok_or_else(|| format!("invalid union branch {index}"))?;
+                self.writer.write_varint(*index as i64)?;
+                return self
+                    .encode(value, self.node(*branch))
+                    .with_segment(|| PathSegment::Branch(*index as usize));
+            }
+            (Node::Union { branches, .. } | Node::Nullable { branches, .. }, _) => {
+                if let Some(index) = branches.iter().position(|b| matches(value, self.node(*b))) {
+                    self.writer.write_varint(index as i64)?;
+                    return self
+                        .encode(value, self.node(branches[index]))
+                        .with_segment(|| PathSegment::Branch(index));
+                }
+            }
+            (_, Value::Union(_, value)) => return self.encode(value, schema),
+            _ if !matches(value, schema) => {}
+            (_, Value::Null) => return Ok(()),
+            (_, Value::Boolean(b)) => return Ok(self.writer.write_all(&[u8::from(*b)])?),
+            (
+                Node::Int | Node::Date | Node::TimeMillis,
+                Value::Int(n) | Value::Date(n) | Value::TimeMillis(n),
+            ) => {
+                self.writer.write_varint(*n)?;
+                return Ok(());
+            }
+            (_, Value::Int(n)) => {
+                self.writer.write_varint(*n as i64)?;
+                return Ok(());
+            }
+            (
+                _,
+                Value::Long(n)
+                | Value::TimeMicros(n)
+                | Value::TimestampMillis(n)
+                | Value::TimestampMicros(n),
+            ) => {
+                self.writer.write_varint(*n)?;
+                return Ok(());
+            }
+            (Node::Float, Value::Float(n)) => {
+                return Ok(self.writer.write_all(&n.to_le_bytes())?)
+            }
+            (_, Value::Float(n)) => {
+                return Ok(self.writer.write_all(&(*n as f64).to_le_bytes())?)
+            }
+            (_, Value::Double(n)) => return Ok(self.writer.write_all(&n.to_le_bytes())?),
+            (_, Value::Bytes(bytes)) => return self.write_bytes(bytes),
+            (_, Value::String(s)) => return self.write_bytes(s.as_bytes()),
+            (Node::Fixed { size, .. }, Value::Fixed(_, bytes)) => {
+                return self.write_fixed(bytes, *size)
+            }
+            (Node::Enum { symbols, .. }, Value::Enum(_, symbol)) => {
+                let index = symbols
+                    .position(symbol)
+                    .ok_or_else(|| format!("unexpected {symbol} in enum"))?;
+                self.writer.write_varint(index as i64)?;
+                return Ok(());
+            }
+            (Node::Array(items), Value::Array(values)) => {
+                if !values.is_empty() {
+                    self.writer.write_varint(values.len() as i64)?;
+                    for (index, value) in values.iter().enumerate() {
+                        self.encode(value, self.node(*items))
+                            .with_segment(|| PathSegment::Index(index))?;
+                    }
+                }
+                return Ok(self.writer.write_all(&[0])?);
+            }
+            (Node::Map(values), Value::Map(entries)) => {
+                if !entries.is_empty() {
+                    self.writer.write_varint(entries.len() as i64)?;
+                    for (key, value) in entries {
+                        self.write_bytes(key.as_bytes())?;
+                        self.encode(value, self.node(*values))
+                            .with_segment(|| PathSegment::Key(key.clone()))?;
+                    }
+                }
+                return Ok(self.writer.write_all(&[0])?);
+            }
+            (Node::Record { fields, .. }, Value::Record(entries)) => {
+                return self.write_record(fields, entries)
+            }
+            (Node::Decimal { inner, .. }, Value::Decimal(decimal)) => {
+                let bytes = Vec::<u8>::try_from(decimal)
+                    .map_err(|error| format!("invalid decimal: {error}"))?;
+                return match self.node(*inner) {
+                    Node::Fixed { size, .. } => {
+                        let bytes = logical::sign_extend(&bytes, *size)
+                            .ok_or_else(|| format!("decimal doesn't fit in fixed {size}"))?;
+                        Ok(self.writer.write_all(&bytes)?)
+                    }
+                    _ => self.write_bytes(&bytes),
+                };
+            }
+            (_, Value::Uuid(uuid)) => {
+                let mut buffer = [0; 36];
+                write!(&mut buffer[..], "{uuid}")?;
+                return self.write_bytes(&buffer);
+            }
+            (_, Value::Duration(duration)) => {
+                return Ok(self.writer.write_all(&<[u8; 12]>::from(*duration))?)
+            }
+            _ => {}
+        }
+        Err(SerializationError::SchemaMismatch {
+            expected: Box::new(schema.to_schema(self.nodes)),
+            found: kind(value),
+            path: Default::default(),
+        })
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, LinkedList, VecDeque},
+    rc::Rc,
+    sync::Arc,
+};
+
+use serde_json::{json, Value};
+
+/// Rust type with an Avro schema, usually derived with [`AvroSchema`](macro@crate::AvroSchema).
+///
+/// The schema matches the way the type is serialized by serde, so a
+/// [`Serializer`](crate::Serializer) built with
+/// [`Serializer::for_type`](crate::Serializer::for_type) accepts it.
+pub trait AvroSchema {
+    /// JSON schema of the type. Named types whose fully qualified name is already in `defined`
+    /// must be referenced by their name, the others must be added to it.
+    fn json_schema(defined: &mut HashSet<String>) -> Value;
+}
+
+/// Type of the schema derived for an enum represented as a union where it is used within its own
+/// definition, which is rejected when parsed as Avro unions cannot be recursive.
+pub const RECURSIVE_UNION: &str = "$avro_poc::private::RecursiveUnion";
+
+macro_rules! primitive {
+    ($($ty:ty => $schema:literal),* $(,)?) => {$(
+        impl AvroSchema for $ty {
+            fn json_schema(_: &mut HashSet<String>) -> Value {
+                Value::String($schema.into())
+            }
+        }
+    )*};
+}
+
+primitive!(
+    () => "null",
+    bool => "boolean",
+    i8 => "int",
+    i16 => "int",
+    i32 => "int",
+    u8 => "int",
+    u16 => "int",
+    u32 => "long",
+    i64 => "long",
+    u64 => "long",
+    f32 => "float",
+    f64 => "double",
+    char => "string",
+    str => "string",
+    String => "string",
+);
+
+macro_rules! transparent {
+    ($($ty:ident),*) => {$(
+        impl<T: AvroSchema + ?Sized> AvroSchema for $ty<T> {
+            fn json_schema(defined: &mut HashSet<String>) -> Value {
+                T::json_schema(defined)
+            }
+        }
+    )*};
+}
+
+transparent!(Box, Rc, Arc);
+
+impl<T: AvroSchema + ?Sized> AvroSchema for &T {
+    fn json_schema(defined: &mut HashSet<String>) -> Value {
+        T::json_schema(defined)
+    }
+}
+
+impl<T: AvroSchema> AvroSchema for Option<T> {
+    fn json_schema(defined: &mut HashSet<String>) -> Value {
+        json!(["null", T::json_schema(defined)])
+    }
+}
+
+macro_rules! array {
+    ($($ty:ty),*) => {$(
+        impl<T: AvroSchema> AvroSchema for $ty {
+            fn json_schema(defined: &mut HashSet<String>) -> Value {
+                json!({"type": "array", "items": T::json_schema(defined)})
+            }
+        }
+    )*};
+}
+
+array!([T], Vec<T>, VecDeque<T>, LinkedList<T>, BTreeSet<T>);
+
+impl<T: AvroSchema, S> AvroSchema for HashSet<T, S> {
+    fn json_schema(defined: &mut HashSet<String>) -> Value {
+        json!({"type": "array", "items": T::json_schema(defined)})
+    }
+}
+
+impl<T: AvroSchema, const N: usize> AvroSchema for [T; N] {
+    fn json_schema(defined: &mut HashSet<String>) -> Value {
+        json!({"type": "array", "items": T::json_schema(defined)})
+    }
+}
+
+impl<T: AvroSchema> AvroSchema for BTreeMap<String, T> {
+    fn json_schema(defined: &mut HashSet<String>) -> Value {
+        json!({"type": "map", "values": T::json_schema(defined)})
+    }
+}
+
+impl<T: AvroSchema, S> AvroSchema for HashMap<String, T, S> {
+    fn json_schema(defined: &mut HashSet<String>) -> Value {
+        json!({"type": "map", "values": T::json_schema(defined)})
+    }
+}
@@ -0,0 +1,59 @@
+/// Binary encodings of several values, stored one after the other in a single buffer.
+///
+/// The batch can be reused with [`Batch::clear`], keeping its allocations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Batch {
+    pub(crate) buffer: Vec<u8>,
+    /// End offset of each value in the buffer.
+    pub(crate) ends: Vec<usize>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        let end = *self.ends.get(index)?;
+        let start = index.checked_sub(1).map_or(0, |i| self.ends[i]);
+        Some(&self.buffer[start..end])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        (0..self.len()).map(|index| self.get(index).unwrap())
+    }
+
+    /// Encodings of all the values, concatenated.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// End offset of each value in [`Batch::buffer`].
+    pub fn ends(&self) -> &[usize] {
+        &self.ends
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.ends.clear();
+    }
+
+    /// Split the buffer into one [`bytes::Bytes`] per value, sharing the same allocation.
+    #[cfg(feature = "bytes")]
+    pub fn into_bytes(self) -> Vec<bytes::Bytes> {
+        let buffer = bytes::Bytes::from(self.buffer);
+        let starts = std::iter::once(0).chain(self.ends.iter().copied());
+        starts
+            .zip(&self.ends)
+            .map(|(start, &end)| buffer.slice(start..end))
+            .collect()
+    }
+}
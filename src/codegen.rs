@@ -0,0 +1,435 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    fs, io,
+    path::Path,
+};
+
+use crate::{
+    schema::{self, Name, SerializationSchema, SerializationSchemaKind},
+    SchemaError, Serializer, SerializerConfig,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CodegenError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Schema(#[from] SchemaError),
+}
+
+/// Generate the Rust types of the `.avsc` files, e.g. from a build script; named types may be
+/// referenced from any of the files.
+pub fn generate_files(paths: &[impl AsRef<Path>]) -> Result<String, CodegenError> {
+    let schemas = paths
+        .iter()
+        .map(fs::read_to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some((root, dependencies)) = schemas.split_first() else {
+        return Ok(String::new());
+    };
+    let dependencies = dependencies.iter().map(String::as_str).collect::<Vec<_>>();
+    let serializer =
+        Serializer::parse_with_dependencies(root, &dependencies, SerializerConfig::default())?;
+    Ok(generate(&serializer))
+}
+
+/// Generate Rust types for the named types of the schema and its dependencies, with serde
+/// derives matching the way the [`Serializer`] serializes them:
+/// - records are structs, enums are enums of unit variants, fixed are byte newtypes;
+/// - unions with `null` are [`Option`]s, other unions enums whose variants are named after the
+///   named types or the kinds of their branches;
+/// - logical types use `uuid`, `chrono` and `rust_decimal` types;
+/// - recursive types are boxed;
+/// - names which would clash, e.g. the `a_b` and `A_B` symbols or a record named like the enum of
+///   a union field, get a number suffix and are renamed to their Avro name.
+///
+/// The generated code depends on the `serde`, `serde_bytes`, `uuid`, `chrono` and
+/// `rust_decimal` crates, with their `serde` features.
+pub fn generate(serializer: &Serializer) -> String {
+    let schemas = &serializer.schema;
+    let mut definitions = Vec::new();
+    for schema in [&schemas.root].into_iter().chain(&schemas.dependencies) {
+        collect_definitions(schema, &mut definitions);
+    }
+    let mut seen = HashSet::new();
+    definitions.retain(|schema| seen.insert(schema.name().unwrap()));
+
+    let mut simple_names = HashMap::<&str, usize>::new();
+    for schema in &definitions {
+        *simple_names
+            .entry(&schema.name().unwrap().name)
+            .or_default() += 1;
+    }
+    let mut type_idents = HashSet::new();
+    let type_names = definitions
+        .iter()
+        .map(|schema| {
+            let name = schema.name().unwrap();
+            let type_name = if simple_names[name.name.as_str()] > 1 {
+                pascal_case(&name.to_string())
+            } else {
+                pascal_case(&name.name)
+            };
+            (name.clone(), unique(&mut type_idents, type_name))
+        })
+        .collect();
+    let edges = definitions
+        .iter()
+        .map(|schema| {
+            let mut names = Vec::new();
+            if let SerializationSchema::Record { fields, .. } = schema {
+                for field in fields {
+                    direct_references(&field.schema, &mut names);
+                }
+            }
+            (schema.name().unwrap().clone(), names)
+        })
+        .collect();
+
+    let mut generator = Generator {
+        definitions: schema::definitions([&schemas.root].into_iter().chain(&schemas.dependencies)),
+        type_names,
+        type_idents,
+        edges,
+        output: String::from("// Generated by avro_poc::codegen, do not edit.\n"),
+    };
+    for schema in definitions {
+        generator.definition(schema);
+    }
+    generator.output
+}
+
+/// Named types defined in the schema, in definition order.
+fn collect_definitions<'a>(
+    schema: &'a SerializationSchema,
+    definitions: &mut Vec<&'a SerializationSchema>,
+) {
+    match schema {
+        SerializationSchema::Array(schema) | SerializationSchema::Map(schema) => {
+            collect_definitions(schema, definitions)
+        }
+        SerializationSchema::Union { schemas } => {
+            for schema in schemas {
+                collect_definitions(schema, definitions);
+            }
+        }
+        SerializationSchema::Record { fields, .. } => {
+            definitions.push(schema);
+            for field in fields {
+                collect_definitions(&field.schema, definitions);
+            }
+        }
+        SerializationSchema::Enum { .. } | SerializationSchema::Fixed { .. } => {
+            definitions.push(schema)
+        }
+        _ => {}
+    }
+}
+
+/// Named types contained without indirection, i.e. not in an array or a map.
+fn direct_references(schema: &SerializationSchema, names: &mut Vec<Name>) {
+    match schema {
+        SerializationSchema::Union { schemas } => {
+            for schema in schemas {
+                direct_references(schema, names);
+            }
+        }
+        SerializationSchema::Record { name, .. } | SerializationSchema::Ref { name } => {
+            names.push(name.clone())
+        }
+        _ => {}
+    }
+}
+
+/// Enum generated for a union which isn't only nullable.
+struct UnionEnum {
+    name: String,
+    variants: Vec<Variant>,
+}
+
+struct Variant {
+    name: String,
+    rename: Option<String>,
+    rust_type: String,
+}
+
+struct Generator<'a> {
+    definitions: HashMap<&'a Name, &'a SerializationSchema>,
+    type_names: HashMap<Name, String>,
+    /// Type names generated so far, including the enums of unions.
+    type_idents: HashSet<String>,
+    /// Named types directly contained by each record.
+    edges: HashMap<Name, Vec<Name>>,
+    output: String,
+}
+
+impl<'a> Generator<'a> {
+    /// Whether `to` is contained without indirection by `from`, so it must be boxed in `to`.
+    fn reaches(&self, from: &Name, to: &Name) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![from];
+        while let Some(name) = stack.pop() {
+            if name == to {
+                return true;
+            }
+            if visited.insert(name) {
+                stack.extend(self.edges.get(name).into_iter().flatten());
+            }
+        }
+        false
+    }
+
+    fn definition(&mut self, schema: &SerializationSchema) {
+        let type_name = self.type_names[schema.name().unwrap()].clone();
+        self.output.push('\n');
+        match schema {
+            SerializationSchema::Record {
+                name, doc, fields, ..
+            } => {
+                let mut unions = Vec::new();
+                let mut body = String::new();
+                let mut idents = HashSet::new();
+                for field in fields {
+                    write_doc(&mut body, field.doc.as_deref(), "    ");
+                    let ident = escape_keyword(&unique(&mut idents, snake_case(&field.name)));
+                    if ident.trim_start_matches("r#") != field.name {
+                        writeln!(body, "    #[serde(rename = {:?})]", field.name).unwrap();
+                    }
+                    let context = format!("{type_name}{}", pascal_case(&field.name));
+                    let rust_type =
+                        self.rust_type(&field.schema, &context, name, false, &mut unions);
+                    writeln!(body, "    pub {ident}: {rust_type},").unwrap();
+                }
+                write_doc(&mut self.output, doc.as_deref(), "");
+                self.output.push_str(
+                    "#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]\n",
+                );
+                writeln!(self.output, "pub struct {type_name} {{\n{body}}}").unwrap();
+                for union in unions {
+                    self.output.push_str(
+                        "\n#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]\n",
+                    );
+                    writeln!(self.output, "pub enum {} {{", union.name).unwrap();
+                    for variant in union.variants {
+                        if let Some(rename) = variant.rename {
+                            writeln!(self.output, "    #[serde(rename = {rename:?})]").unwrap();
+                        }
+                        writeln!(self.output, "    {}({}),", variant.name, variant.rust_type)
+                            .unwrap();
+                    }
+                    self.output.push_str("}\n");
+                }
+            }
+            SerializationSchema::Enum { doc, symbols, .. } => {
+                write_doc(&mut self.output, doc.as_deref(), "");
+                self.output.push_str(
+                    "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]\n",
+                );
+                writeln!(self.output, "pub enum {type_name} {{").unwrap();
+                let mut variants = HashSet::new();
+                for symbol in symbols.iter() {
+                    let variant = unique(&mut variants, pascal_case(symbol));
+                    if variant != *symbol {
+                        writeln!(self.output, "    #[serde(rename = {symbol:?})]").unwrap();
+                    }
+                    writeln!(self.output, "    {variant},").unwrap();
+                }
+                self.output.push_str("}\n");
+            }
+            SerializationSchema::Fixed { doc, size, .. } => {
+                write_doc(&mut self.output, doc.as_deref(), "");
+                writeln!(self.output, "/// Fixed of {size} bytes.").unwrap();
+                self.output.push_str(
+                    "#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]\n",
+                );
+                writeln!(
+                    self.output,
+                    "pub struct {type_name}(#[serde(with = \"serde_bytes\")] pub Vec<u8>);"
+                )
+                .unwrap();
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Rust type of a schema contained by the `owner` record; `indirect` is set in arrays and
+    /// maps, and the enums generated for unions are added to `unions`.
+    fn rust_type(
+        &mut self,
+        schema: &SerializationSchema,
+        context: &str,
+        owner: &Name,
+        indirect: bool,
+        unions: &mut Vec<UnionEnum>,
+    ) -> String {
+        match schema {
+            SerializationSchema::Null => "()".into(),
+            SerializationSchema::Boolean => "bool".into(),
+            SerializationSchema::Int => "i32".into(),
+            SerializationSchema::Long => "i64".into(),
+            SerializationSchema::Float => "f32".into(),
+            SerializationSchema::Double => "f64".into(),
+            SerializationSchema::Bytes | SerializationSchema::Duration { .. } => {
+                "serde_bytes::ByteBuf".into()
+            }
+            SerializationSchema::String => "String".into(),
+            SerializationSchema::Uuid => "uuid::Uuid".into(),
+            SerializationSchema::Date => "chrono::NaiveDate".into(),
+            SerializationSchema::TimeMillis | SerializationSchema::TimeMicros => {
+                "chrono::NaiveTime".into()
+            }
+            SerializationSchema::TimestampMillis | SerializationSchema::TimestampMicros => {
+                "chrono::DateTime<chrono::Utc>".into()
+            }
+            SerializationSchema::Decimal { .. } => "rust_decimal::Decimal".into(),
+            SerializationSchema::Array(items) => {
+                let items = self.rust_type(items, &format!("{context}Item"), owner, true, unions);
+                format!("Vec<{items}>")
+            }
+            SerializationSchema::Map(values) => {
+                let values =
+                    self.rust_type(values, &format!("{context}Value"), owner, true, unions);
+                format!("std::collections::HashMap<String, {values}>")
+            }
+            // Logical types backed by a named fixed have no definition of their own.
+            SerializationSchema::Ref { name } if !self.type_names.contains_key(name) => {
+                let definition = self.definitions[name];
+                self.rust_type(definition, context, owner, indirect, unions)
+            }
+            SerializationSchema::Record { name, .. }
+            | SerializationSchema::Enum { name, .. }
+            | SerializationSchema::Fixed { name, .. }
+            | SerializationSchema::Ref { name } => {
+                let type_name = &self.type_names[name];
+                if !indirect && self.reaches(name, owner) {
+                    format!("Box<{type_name}>")
+                } else {
+                    type_name.clone()
+                }
+            }
+            SerializationSchema::Union { schemas } => {
+                let branches = schemas
+                    .iter()
+                    .filter(|schema| !matches!(schema, SerializationSchema::Null))
+                    .collect::<Vec<_>>();
+                let nullable = branches.len() < schemas.len();
+                let rust_type = match branches[..] {
+                    [] => return "()".into(),
+                    [branch] => self.rust_type(branch, context, owner, indirect, unions),
+                    _ => {
+                        let enum_name = unique(&mut self.type_idents, context.to_string());
+                        let mut names = HashSet::new();
+                        let mut variants = Vec::new();
+                        for branch in branches {
+                            let defined = branch
+                                .name()
+                                .filter(|name| self.type_names.contains_key(*name));
+                            let (avro_name, variant) = match defined {
+                                Some(name) => (name.to_string(), self.type_names[name].clone()),
+                                None => {
+                                    let kind = match branch {
+                                        SerializationSchema::Ref { name } => {
+                                            SerializationSchemaKind::from(self.definitions[name])
+                                        }
+                                        _ => SerializationSchemaKind::from(branch),
+                                    };
+                                    let kind = <&str>::from(kind);
+                                    (kind.to_string(), kind.to_string())
+                                }
+                            };
+                            let name = unique(&mut names, variant);
+                            let rename = (name != avro_name
+                                && defined.is_none_or(|defined| name != defined.name))
+                            .then_some(avro_name);
+                            let context = format!("{enum_name}{name}");
+                            let rust_type =
+                                self.rust_type(branch, &context, owner, indirect, unions);
+                            variants.push(Variant {
+                                name,
+                                rename,
+                                rust_type,
+                            });
+                        }
+                        unions.push(UnionEnum {
+                            name: enum_name.clone(),
+                            variants,
+                        });
+                        enum_name
+                    }
+                };
+                if nullable {
+                    format!("Option<{rust_type}>")
+                } else {
+                    rust_type
+                }
+            }
+        }
+    }
+}
+
+fn write_doc(output: &mut String, doc: Option<&str>, indent: &str) {
+    for line in doc.into_iter().flat_map(str::lines) {
+        writeln!(output, "{indent}/// {}", line.trim()).unwrap();
+    }
+}
+
+/// `fooBar`, `foo_bar` and `FOO_BAR` all give `FooBar`; dots are treated as underscores.
+fn pascal_case(name: &str) -> String {
+    name.split(['_', '.'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let (first, rest) = word.split_at(1);
+            let rest = if word.chars().all(|c| !c.is_lowercase()) {
+                rest.to_lowercase()
+            } else {
+                rest.to_string()
+            };
+            first.to_uppercase() + &rest
+        })
+        .collect()
+}
+
+/// `name`, or `name` followed by the lowest number from 2 making it unique among the `used` ones.
+fn unique(used: &mut HashSet<String>, name: String) -> String {
+    let mut candidate = name.clone();
+    for number in 2.. {
+        if used.insert(candidate.clone()) {
+            break;
+        }
+        candidate = format!("{name}{number}");
+    }
+    candidate
+}
+
+/// `fooBar`, `FooBar` and `FOO_BAR` give `foo_bar`, `foo_bar` and `foo_bar`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let chars = name.chars().collect::<Vec<_>>();
+    for (i, c) in chars.iter().enumerate() {
+        let previous_lowercase =
+            i > 0 && (chars[i - 1].is_lowercase() || chars[i - 1].is_ascii_digit());
+        let next_lowercase = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+        let previous_uppercase = i > 0 && chars[i - 1].is_uppercase();
+        if c.is_uppercase() && (previous_lowercase || previous_uppercase && next_lowercase) {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+    "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type", "unsafe", "use",
+    "where", "while", "yield",
+];
+
+fn escape_keyword(ident: &str) -> String {
+    match ident {
+        "crate" | "self" | "super" => format!("{ident}_"),
+        _ if KEYWORDS.contains(&ident) => format!("r#{ident}"),
+        _ => ident.to_string(),
+    }
+}
@@ -0,0 +1,357 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    node::{Node, NodeId},
+    schema::{Name, SerializationSchemaKind},
+    Serializer,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatibilityLevel {
+    /// The new schema can read data written with the latest one.
+    Backward,
+    /// The new schema can read data written with all the previous ones.
+    BackwardTransitive,
+    /// The latest schema can read data written with the new one.
+    Forward,
+    /// All the previous schemas can read data written with the new one.
+    ForwardTransitive,
+    /// Both backward and forward.
+    Full,
+    /// Both backward and forward transitive.
+    FullTransitive,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IncompatibilityKind {
+    #[error("reader type {reader} cannot read writer type {writer}")]
+    TypeMismatch {
+        reader: SerializationSchemaKind,
+        writer: SerializationSchemaKind,
+    },
+    #[error("reader name {reader} doesn't match writer name {writer}")]
+    NameMismatch { reader: Name, writer: Name },
+    #[error("reader fixed size {reader} doesn't match writer fixed size {writer}")]
+    FixedSizeMismatch { reader: usize, writer: usize },
+    #[error("writer enum symbols {0:?} are missing in reader enum without default")]
+    MissingEnumSymbols(Vec<String>),
+    #[error("reader field {0} is missing in writer and has no default")]
+    MissingDefault(String),
+    #[error("no reader union branch matches writer {0}")]
+    MissingUnionBranch(SerializationSchemaKind),
+}
+
+/// Incompatibility found between a reader and a writer schema; the path is made of the type and
+/// field names leading to the incompatible schemas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Incompatibility {
+    pub path: Vec<String>,
+    pub kind: IncompatibilityKind,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (path: {:?})", self.kind, self.path)
+    }
+}
+
+/// Incompatibility between a new schema and a version of the history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionIncompatibility {
+    /// Index of the version in the history.
+    pub version: usize,
+    /// Whether the new schema is the reader (backward) or the writer (forward).
+    pub backward: bool,
+    pub incompatibility: Incompatibility,
+}
+
+/// Check that data written with `writer` can be read with `reader`, following the schema
+/// resolution rules of the specification.
+pub fn check(reader: &Serializer, writer: &Serializer) -> Vec<Incompatibility> {
+    let mut checker = Checker {
+        reader_nodes: &reader.schema.nodes,
+        writer_nodes: &writer.schema.nodes,
+        visited: HashSet::new(),
+        path: Vec::new(),
+        incompatibilities: Vec::new(),
+    };
+    checker.check(reader.schema.root_node(), writer.schema.root_node());
+    checker.incompatibilities
+}
+
+/// Check a new schema against the `history` of the previous ones, ordered from the oldest to
+/// the latest.
+pub fn check_history(
+    schema: &Serializer,
+    history: &[Serializer],
+    level: CompatibilityLevel,
+) -> Vec<VersionIncompatibility> {
+    use CompatibilityLevel::*;
+    let versions = match level {
+        Backward | Forward | Full => history.len().saturating_sub(1)..history.len(),
+        BackwardTransitive | ForwardTransitive | FullTransitive => 0..history.len(),
+    };
+    let mut incompatibilities = Vec::new();
+    for version in versions {
+        let previous = &history[version];
+        let mut add = |backward, found: Vec<Incompatibility>| {
+            incompatibilities.extend(found.into_iter().map(|incompatibility| {
+                VersionIncompatibility {
+                    version,
+                    backward,
+                    incompatibility,
+                }
+            }))
+        };
+        if matches!(level, Backward | BackwardTransitive | Full | FullTransitive) {
+            add(true, check(schema, previous));
+        }
+        if matches!(level, Forward | ForwardTransitive | Full | FullTransitive) {
+            add(false, check(previous, schema));
+        }
+    }
+    incompatibilities
+}
+
+struct Checker<'a> {
+    reader_nodes: &'a [Node],
+    writer_nodes: &'a [Node],
+    /// Record pairs already being checked, to stop on recursive types.
+    visited: HashSet<(*const Node, *const Node)>,
+    path: Vec<String>,
+    incompatibilities: Vec<Incompatibility>,
+}
+
+impl<'a> Checker<'a> {
+    fn add(&mut self, kind: IncompatibilityKind) {
+        self.incompatibilities.push(Incompatibility {
+            path: self.path.clone(),
+            kind,
+        });
+    }
+
+    /// Probe whether the reader can read the writer, forgetting the incompatibilities found and
+    /// the records visited meanwhile.
+    fn compatible(&mut self, reader: &'a Node, writer: &'a Node) -> bool {
+        let count = self.incompatibilities.len();
+        let visited = self.visited.clone();
+        self.check(reader, writer);
+        let compatible = self.incompatibilities.len() == count;
+        self.incompatibilities.truncate(count);
+        self.visited = visited;
+        compatible
+    }
+
+    fn check(&mut self, reader: &'a Node, writer: &'a Node) {
+        let reader = underlying(reader, self.reader_nodes);
+        let writer = underlying(writer, self.writer_nodes);
+        match (reader, writer) {
+            (
+                Node::Union {
+                    branches: readers, ..
+                }
+                | Node::Nullable {
+                    branches: readers, ..
+                },
+                Node::Union {
+                    branches: writers, ..
+                }
+                | Node::Nullable {
+                    branches: writers, ..
+                },
+            ) => {
+                for writer in writers {
+                    let writer = self.writer(*writer);
+                    if !readers
+                        .iter()
+                        .any(|reader| self.compatible(self.reader(*reader), writer))
+                    {
+                        let writer = underlying(writer, self.writer_nodes);
+                        self.add(IncompatibilityKind::MissingUnionBranch(writer.into()));
+                    }
+                }
+            }
+            (
+                _,
+                Node::Union {
+                    branches: writers, ..
+                }
+                | Node::Nullable {
+                    branches: writers, ..
+                },
+            ) => {
+                for writer in writers {
+                    self.check(reader, self.writer(*writer));
+                }
+            }
+            (
+                Node::Union {
+                    branches: readers, ..
+                }
+                | Node::Nullable {
+                    branches: readers, ..
+                },
+                _,
+            ) => {
+                if !readers
+                    .iter()
+                    .any(|reader| self.compatible(self.reader(*reader), writer))
+                {
+                    self.add(IncompatibilityKind::MissingUnionBranch(writer.into()));
+                }
+            }
+            (Node::Array(reader), Node::Array(writer)) | (Node::Map(reader), Node::Map(writer)) => {
+                self.check(self.reader(*reader), self.writer(*writer))
+            }
+            (
+                Node::Record {
+                    named,
+                    fields: reader_fields,
+                    ..
+                },
+                Node::Record {
+                    fields: writer_fields,
+                    ..
+                },
+            ) => {
+                if !self.check_name(reader, writer)
+                    || !self
+                        .visited
+                        .insert((reader as *const _, writer as *const _))
+                {
+                    return;
+                }
+                self.path.push(named.name.name.clone());
+                for field in reader_fields {
+                    let writer_field = writer_fields
+                        .iter()
+                        .find(|f| f.name == field.name || field.aliases.contains(&f.name));
+                    self.path.push(field.name.clone());
+                    match writer_field {
+                        Some(writer_field) => {
+                            self.check(self.reader(field.node), self.writer(writer_field.node))
+                        }
+                        None if field.default.is_none() => {
+                            self.add(IncompatibilityKind::MissingDefault(field.name.clone()))
+                        }
+                        None => {}
+                    }
+                    self.path.pop();
+                }
+                self.path.pop();
+            }
+            (
+                Node::Enum {
+                    symbols: reader_symbols,
+                    default,
+                    ..
+                },
+                Node::Enum {
+                    symbols: writer_symbols,
+                    ..
+                },
+            ) => {
+                if !self.check_name(reader, writer) || default.is_some() {
+                    return;
+                }
+                let missing = writer_symbols
+                    .iter()
+                    .filter(|symbol| !reader_symbols.contains(symbol))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !missing.is_empty() {
+                    self.add(IncompatibilityKind::MissingEnumSymbols(missing));
+                }
+            }
+            (
+                Node::Fixed {
+                    size: reader_size, ..
+                },
+                Node::Fixed {
+                    size: writer_size, ..
+                },
+            ) => {
+                if self.check_name(reader, writer) && reader_size != writer_size {
+                    self.add(IncompatibilityKind::FixedSizeMismatch {
+                        reader: *reader_size,
+                        writer: *writer_size,
+                    });
+                }
+            }
+            _ => {
+                let (reader_kind, writer_kind) = (primitive(reader), primitive(writer));
+                if !promotable(reader_kind, writer_kind) {
+                    self.add(IncompatibilityKind::TypeMismatch {
+                        reader: reader_kind,
+                        writer: writer_kind,
+                    });
+                }
+            }
+        }
+    }
+
+    fn reader(&self, id: NodeId) -> &'a Node {
+        &self.reader_nodes[id as usize]
+    }
+
+    fn writer(&self, id: NodeId) -> &'a Node {
+        &self.writer_nodes[id as usize]
+    }
+
+    /// Names are compared unqualified, as the specification requires, while aliases are full
+    /// names, compared with the full writer name as in the reference implementation.
+    fn check_name(&mut self, reader: &Node, writer: &Node) -> bool {
+        let (Some(reader_name), Some(writer_name)) = (reader.name(), writer.name()) else {
+            unreachable!()
+        };
+        if reader_name.name == writer_name.name
+            || reader.aliases().iter().any(|alias| alias == writer_name)
+        {
+            return true;
+        }
+        self.add(IncompatibilityKind::NameMismatch {
+            reader: reader_name.clone(),
+            writer: writer_name.clone(),
+        });
+        false
+    }
+}
+
+/// Resolve decimals and durations, whose resolution only depends on their underlying type.
+fn underlying<'a>(schema: &'a Node, nodes: &'a [Node]) -> &'a Node {
+    match schema {
+        Node::Decimal { inner, .. } | Node::Duration { inner } => {
+            underlying(&nodes[*inner as usize], nodes)
+        }
+        _ => schema,
+    }
+}
+
+/// Logical types are resolved as their underlying primitive type.
+fn primitive(schema: &Node) -> SerializationSchemaKind {
+    match schema {
+        Node::Uuid => SerializationSchemaKind::String,
+        Node::Date | Node::TimeMillis => SerializationSchemaKind::Int,
+        Node::TimeMicros | Node::TimestampMillis | Node::TimestampMicros => {
+            SerializationSchemaKind::Long
+        }
+        _ => schema.into(),
+    }
+}
+
+fn promotable(reader: SerializationSchemaKind, writer: SerializationSchemaKind) -> bool {
+    use SerializationSchemaKind::*;
+    let is_primitive = matches!(
+        reader,
+        Null | Boolean | Int | Long | Float | Double | Bytes | String
+    );
+    (reader == writer && is_primitive)
+        || matches!(
+            (reader, writer),
+            (Long, Int)
+                | (Float, Int | Long)
+                | (Double, Int | Long | Float)
+                | (Bytes, String)
+                | (String, Bytes)
+        )
+}
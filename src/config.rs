@@ -0,0 +1,31 @@
+/// How Rust enum variants are mapped to Avro.
+///
+/// Whatever the representation, a variant serialized with a union schema selects the branch
+/// named after it (see [`VariantRepresentation::Union`]); the representation applies when the
+/// variant is serialized with a record schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariantRepresentation {
+    /// Record with a `tag` field holding the variant name, and a `content` field holding the
+    /// variant value.
+    AdjacentlyTagged { tag: String, content: String },
+    /// Record whose first field, named `tag`, holds the variant name, followed by the variant
+    /// fields. Only unit, newtype (of struct) and struct variants are supported.
+    InternallyTagged { tag: String },
+    /// Union branch selected by the variant name, either by named type or by kind; unit variants
+    /// are serialized as enum symbol or null.
+    Union,
+}
+
+impl Default for VariantRepresentation {
+    fn default() -> Self {
+        Self::AdjacentlyTagged {
+            tag: "type".into(),
+            content: "value".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SerializerConfig {
+    pub variant_representation: VariantRepresentation,
+}
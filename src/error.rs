@@ -1,22 +1,109 @@
-use std::{collections::VecDeque, fmt::Display, io};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display},
+    io,
+};
 
-use crate::{schema::SerializationSchema, SerializationSchemaKind};
+use crate::{
+    schema::{Name, SerializationSchema},
+    SerializationSchemaKind,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum SerializationError {
     #[error(transparent)]
     Io(#[from] io::Error),
-    #[error("expected {expected:?}, found {found} (path: {path:?})")]
+    #[error("expected {expected:?}, found {found} (path: {})", DisplayPath(.path))]
     SchemaMismatch {
-        expected: SerializationSchema,
+        expected: Box<SerializationSchema>,
         found: SerializationSchemaKind,
-        path: VecDeque<&'static str>,
+        path: VecDeque<PathSegment>,
     },
-    #[error("{error} (path: {path:?})")]
+    #[error("{error} (path: {})", DisplayPath(.path))]
     Custom {
         error: String,
-        path: VecDeque<&'static str>,
+        path: VecDeque<PathSegment>,
     },
+    /// Errors of the fields and items of a value, when collecting every error instead of stopping
+    /// at the first one.
+    #[error("{} errors, first: {}", .0.len(), .0[0])]
+    Multiple(Vec<SerializationError>),
+    /// The encoded value doesn't fit in the provided buffer.
+    #[error("buffer too small: {needed} bytes needed, {len} available")]
+    BufferTooSmall { len: usize, needed: usize },
+    /// Invalid JSON input, with its line and column.
+    #[error(transparent)]
+    Json(serde_json::Error),
+}
+
+/// I/O errors of the underlying reader or writer are kept as such.
+impl From<serde_json::Error> for SerializationError {
+    fn from(error: serde_json::Error) -> Self {
+        if error.is_io() {
+            SerializationError::Io(error.into())
+        } else {
+            SerializationError::Json(error)
+        }
+    }
+}
+
+impl SerializationError {
+    /// Flatten collected errors.
+    pub(crate) fn into_errors(self) -> Vec<SerializationError> {
+        match self {
+            SerializationError::Multiple(errors) => errors,
+            error => vec![error],
+        }
+    }
+
+    pub(crate) fn from_errors(mut errors: Vec<SerializationError>) -> Result<(), Self> {
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.pop().unwrap()),
+            _ => Err(SerializationError::Multiple(errors)),
+        }
+    }
+}
+
+/// Location of a serialization error in the serialized value, from its root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// Name of the serialized type, e.g. a struct or an enum.
+    Type(&'static str),
+    /// Struct field or enum variant.
+    Field(&'static str),
+    /// Array item, or map entry when its key is not known.
+    Index(usize),
+    /// Map entry, or record field serialized from a map.
+    Key(String),
+    /// Selected union branch.
+    Branch(usize),
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Type(name) | PathSegment::Field(name) => write!(f, "{name}"),
+            PathSegment::Index(index) => write!(f, "[{index}]"),
+            PathSegment::Key(key) => write!(f, "[{key:?}]"),
+            PathSegment::Branch(index) => write!(f, "<{index}>"),
+        }
+    }
+}
+
+/// Path displayed like `Foo.bar[0]<1>.baz`.
+struct DisplayPath<'a>(&'a VecDeque<PathSegment>);
+
+impl Display for DisplayPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            if i > 0 && matches!(segment, PathSegment::Type(_) | PathSegment::Field(_)) {
+                write!(f, ".")?;
+            }
+            write!(f, "{segment}")?;
+        }
+        Ok(())
+    }
 }
 
 impl serde::ser::Error for SerializationError {
@@ -31,6 +118,15 @@ impl serde::ser::Error for SerializationError {
     }
 }
 
+impl serde::de::Error for SerializationError {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        serde::ser::Error::custom(msg)
+    }
+}
+
 impl From<&str> for SerializationError {
     fn from(s: &str) -> Self {
         use serde::ser::Error;
@@ -44,3 +140,31 @@ impl From<String> for SerializationError {
         Self::custom(s)
     }
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("{error} (path: {path:?})")]
+    Invalid {
+        error: String,
+        path: VecDeque<String>,
+    },
+    #[error("unresolved reference to {0}")]
+    UnresolvedRef(Name),
+}
+
+impl From<&str> for SchemaError {
+    fn from(s: &str) -> Self {
+        s.to_string().into()
+    }
+}
+
+impl From<String> for SchemaError {
+    fn from(s: String) -> Self {
+        SchemaError::Invalid {
+            error: s,
+            path: Default::default(),
+        }
+    }
+}
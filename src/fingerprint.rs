@@ -0,0 +1,219 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use md5::{Digest, Md5};
+use sha2::Sha256;
+
+use crate::{
+    node::CompiledSchema,
+    schema::{self, Name, SerializationSchema, SerializationSchemas},
+};
+
+/// Fingerprints of the [Parsing Canonical Form](https://avro.apache.org/docs/current/specification/#parsing-canonical-form-for-schemas)
+/// of a schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint {
+    /// CRC-64-AVRO, used by single-object encoding.
+    pub rabin: u64,
+    pub md5: [u8; 16],
+    pub sha256: [u8; 32],
+}
+
+impl Fingerprint {
+    pub fn new(schema: &SerializationSchema) -> Self {
+        Self::of_canonical_form(&schema.canonical_form())
+    }
+
+    pub(crate) fn of_canonical_form(canonical_form: &str) -> Self {
+        Self {
+            rabin: rabin(canonical_form.as_bytes()),
+            md5: Md5::digest(canonical_form).into(),
+            sha256: Sha256::digest(canonical_form).into(),
+        }
+    }
+}
+
+const EMPTY: u64 = 0xc15d213aa4d7a795;
+
+const RABIN_TABLE: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut fp = i as u64;
+        let mut j = 0;
+        while j < 8 {
+            fp = (fp >> 1) ^ (EMPTY & (fp & 1).wrapping_neg());
+            j += 1;
+        }
+        table[i] = fp;
+        i += 1;
+    }
+    table
+};
+
+pub fn rabin(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(EMPTY, |fp, b| {
+        (fp >> 8) ^ RABIN_TABLE[((fp ^ *b as u64) & 0xff) as usize]
+    })
+}
+
+impl SerializationSchema {
+    /// Logical types, documentation, aliases, defaults and custom attributes are stripped;
+    /// named types are written with their fully qualified name.
+    ///
+    /// References are written as names, so types defined in dependencies are missing; see
+    /// [`Serializer::canonical_form`](crate::Serializer::canonical_form) to inline them.
+    pub fn canonical_form(&self) -> String {
+        let mut writer = CanonicalFormWriter::new(HashMap::new());
+        writer.write(self);
+        writer.buf
+    }
+}
+
+impl SerializationSchemas {
+    pub(crate) fn canonical_form(&self) -> String {
+        canonical_form(&self.root, &self.dependencies)
+    }
+}
+
+impl CompiledSchema {
+    pub(crate) fn canonical_form(&self) -> String {
+        canonical_form(&self.root, &self.dependencies)
+    }
+}
+
+/// Referenced types are written in full at their first occurrence, wherever they are defined,
+/// and by name afterwards.
+fn canonical_form(root: &SerializationSchema, dependencies: &[SerializationSchema]) -> String {
+    let definitions = schema::definitions([root].into_iter().chain(dependencies));
+    let mut writer = CanonicalFormWriter::new(definitions);
+    writer.write(root);
+    writer.buf
+}
+
+struct CanonicalFormWriter<'a> {
+    /// Definitions of the referenced types, or none to write references as names.
+    definitions: HashMap<&'a Name, &'a SerializationSchema>,
+    written: HashSet<&'a Name>,
+    buf: String,
+}
+
+impl<'a> CanonicalFormWriter<'a> {
+    fn new(definitions: HashMap<&'a Name, &'a SerializationSchema>) -> Self {
+        Self {
+            definitions,
+            written: HashSet::new(),
+            buf: String::new(),
+        }
+    }
+
+    /// Whether the named type is written for the first time, otherwise its name is written.
+    fn first_occurrence(&mut self, name: &'a Name) -> bool {
+        if self.written.insert(name) {
+            return true;
+        }
+        self.buf.push_str(&json(name));
+        false
+    }
+
+    fn write(&mut self, schema: &'a SerializationSchema) {
+        match schema {
+            SerializationSchema::Null => self.buf.push_str(r#""null""#),
+            SerializationSchema::Boolean => self.buf.push_str(r#""boolean""#),
+            SerializationSchema::Int
+            | SerializationSchema::Date
+            | SerializationSchema::TimeMillis => self.buf.push_str(r#""int""#),
+            SerializationSchema::Long
+            | SerializationSchema::TimeMicros
+            | SerializationSchema::TimestampMillis
+            | SerializationSchema::TimestampMicros => self.buf.push_str(r#""long""#),
+            SerializationSchema::Float => self.buf.push_str(r#""float""#),
+            SerializationSchema::Double => self.buf.push_str(r#""double""#),
+            SerializationSchema::Bytes => self.buf.push_str(r#""bytes""#),
+            SerializationSchema::String | SerializationSchema::Uuid => {
+                self.buf.push_str(r#""string""#)
+            }
+            SerializationSchema::Array(items) => {
+                self.buf.push_str(r#"{"type":"array","items":"#);
+                self.write(items);
+                self.buf.push('}');
+            }
+            SerializationSchema::Map(values) => {
+                self.buf.push_str(r#"{"type":"map","values":"#);
+                self.write(values);
+                self.buf.push('}');
+            }
+            SerializationSchema::Union { schemas } => {
+                self.buf.push('[');
+                for (i, schema) in schemas.iter().enumerate() {
+                    if i > 0 {
+                        self.buf.push(',');
+                    }
+                    self.write(schema);
+                }
+                self.buf.push(']');
+            }
+            SerializationSchema::Record { name, fields, .. } => {
+                if !self.first_occurrence(name) {
+                    return;
+                }
+                write!(
+                    self.buf,
+                    r#"{{"name":{},"type":"record","fields":["#,
+                    json(name)
+                )
+                .unwrap();
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        self.buf.push(',');
+                    }
+                    write!(self.buf, r#"{{"name":{},"type":"#, json(&field.name)).unwrap();
+                    self.write(&field.schema);
+                    self.buf.push('}');
+                }
+                self.buf.push_str("]}");
+            }
+            SerializationSchema::Enum { name, symbols, .. } => {
+                if !self.first_occurrence(name) {
+                    return;
+                }
+                write!(
+                    self.buf,
+                    r#"{{"name":{},"type":"enum","symbols":["#,
+                    json(name)
+                )
+                .unwrap();
+                for (i, symbol) in symbols.iter().enumerate() {
+                    if i > 0 {
+                        self.buf.push(',');
+                    }
+                    self.buf.push_str(&json(symbol));
+                }
+                self.buf.push_str("]}");
+            }
+            SerializationSchema::Fixed { name, size, .. } => {
+                if !self.first_occurrence(name) {
+                    return;
+                }
+                write!(
+                    self.buf,
+                    r#"{{"name":{},"type":"fixed","size":{size}}}"#,
+                    json(name)
+                )
+                .unwrap();
+            }
+            SerializationSchema::Decimal { inner, .. }
+            | SerializationSchema::Duration { inner } => self.write(inner),
+            SerializationSchema::Ref { name } => match self.definitions.get(name).copied() {
+                Some(schema) => self.write(schema),
+                None => self.buf.push_str(&json(name)),
+            },
+        }
+    }
+}
+
+fn json(s: impl ToString) -> String {
+    serde_json::Value::String(s.to_string()).to_string()
+}
@@ -0,0 +1,621 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    schema::{name_matches, Name},
+    SchemaError, Serializer, SerializerConfig,
+};
+
+/// Protocol, or schemas when the file doesn't declare a protocol, parsed from
+/// [Avro IDL](https://avro.apache.org/docs/current/idl-language/).
+#[derive(Debug, Clone, Default)]
+pub struct Protocol {
+    /// Name of the protocol, `None` for a file declaring schemas.
+    pub name: Option<Name>,
+    pub doc: Option<String>,
+    /// Main schema, declared with `schema <type>;`.
+    pub schema: Option<Value>,
+    /// JSON schemas of the named types, including the imported ones, in declaration order.
+    pub types: Vec<Value>,
+    pub messages: Map<String, Value>,
+    /// Annotations of the protocol other than the namespace.
+    pub attributes: Map<String, Value>,
+}
+
+impl Protocol {
+    /// JSON protocol, as in `.avpr` files.
+    pub fn to_json(&self) -> Value {
+        let mut protocol = self.attributes.clone();
+        if let Some(name) = &self.name {
+            protocol.insert("protocol".into(), name.name.clone().into());
+            if let Some(namespace) = &name.namespace {
+                protocol.insert("namespace".into(), namespace.clone().into());
+            }
+        }
+        if let Some(doc) = &self.doc {
+            protocol.insert("doc".into(), doc.clone().into());
+        }
+        protocol.insert("types".into(), self.types.clone().into());
+        protocol.insert("messages".into(), self.messages.clone().into());
+        Value::Object(protocol)
+    }
+
+    /// Build a [`Serializer`] for one of the named types, or for the main schema when `name` is
+    /// `None`; the other named types can be referenced.
+    pub fn serializer(
+        &self,
+        name: Option<&str>,
+        config: SerializerConfig,
+    ) -> Result<Serializer, SchemaError> {
+        let Some(name) = name else {
+            let schema = self.schema.as_ref().ok_or("no main schema declared")?;
+            return Serializer::parse_json(schema, &self.types, config);
+        };
+        let index = self
+            .types
+            .iter()
+            .position(|schema| type_name(schema).is_some_and(|n| name_matches(&n, name)))
+            .ok_or_else(|| format!("unknown type {name}"))?;
+        let mut dependencies = self.types.clone();
+        let schema = dependencies.remove(index);
+        Serializer::parse_json(&schema, &dependencies, config)
+    }
+}
+
+fn type_name(schema: &Value) -> Option<Name> {
+    let name = Name::new(schema.get("name")?.as_str()?);
+    Some(match schema.get("namespace").and_then(Value::as_str) {
+        Some(namespace) if name.namespace.is_none() => Name {
+            namespace: Some(namespace.into()),
+            ..name
+        },
+        _ => name,
+    })
+}
+
+/// Parse an IDL file; imports are relative to the current directory.
+pub fn parse(input: &str) -> Result<Protocol, SchemaError> {
+    Parser::new(input, PathBuf::new(), &mut HashSet::new()).parse()
+}
+
+/// Parse an IDL file; imports are relative to the directory of the file.
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Protocol, SchemaError> {
+    let path = path.as_ref();
+    let input = read(path)?;
+    let mut imported = HashSet::from([canonical(path)]);
+    Parser::new(&input, directory(path), &mut imported).parse()
+}
+
+fn read(path: &Path) -> Result<String, SchemaError> {
+    fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()).into())
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn directory(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    /// Last documentation comment, attached to the next declaration.
+    doc: Option<String>,
+    namespace: Option<String>,
+    directory: PathBuf,
+    /// Canonical paths of the imported files, which are only imported once.
+    imported: &'a mut HashSet<PathBuf>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, directory: PathBuf, imported: &'a mut HashSet<PathBuf>) -> Self {
+        Self {
+            input,
+            position: 0,
+            doc: None,
+            namespace: None,
+            directory,
+            imported,
+        }
+    }
+
+    fn error(&self, error: impl std::fmt::Display) -> SchemaError {
+        let line = self.input[..self.position].matches('\n').count() + 1;
+        format!("line {line}: {error}").into()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    /// Skip whitespace and comments, keeping the documentation comments.
+    fn skip_whitespace(&mut self) -> Result<(), SchemaError> {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.position += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if let Some(comment) = trimmed.strip_prefix("/*") {
+                let end = comment
+                    .find("*/")
+                    .ok_or_else(|| self.error("unterminated comment"))?;
+                if let Some(doc) = comment[..end].strip_prefix('*') {
+                    self.doc = Some(doc_comment(doc));
+                }
+                self.position += end + 4;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn eat(&mut self, c: char) -> Result<bool, SchemaError> {
+        self.skip_whitespace()?;
+        let found = self.rest().starts_with(c);
+        if found {
+            self.position += c.len_utf8();
+        }
+        Ok(found)
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), SchemaError> {
+        if !self.eat(c)? {
+            return Err(self.error(format!("expected '{c}'")));
+        }
+        Ok(())
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<bool, SchemaError> {
+        self.skip_whitespace()?;
+        let found = self
+            .rest()
+            .strip_prefix(keyword)
+            .is_some_and(|rest| !rest.starts_with(is_identifier_char));
+        if found {
+            self.position += keyword.len();
+        }
+        Ok(found)
+    }
+
+    /// Possibly dotted identifier, which may be quoted with backticks.
+    fn identifier(&mut self) -> Result<String, SchemaError> {
+        self.skip_whitespace()?;
+        let rest = self.rest();
+        if let Some(quoted) = rest.strip_prefix('`') {
+            let end = quoted
+                .find('`')
+                .ok_or_else(|| self.error("unterminated identifier"))?;
+            self.position += end + 2;
+            return Ok(quoted[..end].to_string());
+        }
+        let len = rest.find(|c| !is_identifier_char(c)).unwrap_or(rest.len());
+        if len == 0 || rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(self.error("expected identifier"));
+        }
+        self.position += len;
+        Ok(rest[..len].to_string())
+    }
+
+    fn json(&mut self) -> Result<Value, SchemaError> {
+        self.skip_whitespace()?;
+        let rest = self.rest();
+        // The stream deserializer only accepts scalars followed by JSON delimiters.
+        if !rest.starts_with(['{', '[', '"']) {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c)))
+                .unwrap_or(rest.len());
+            let scalar = serde_json::from_str(&rest[..len]).map_err(|e| self.error(e))?;
+            self.position += len;
+            return Ok(scalar);
+        }
+        let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+        match stream.next() {
+            Some(Ok(value)) => {
+                self.position += stream.byte_offset();
+                Ok(value)
+            }
+            Some(Err(e)) => Err(self.error(e)),
+            None => Err(self.error("expected JSON value")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, SchemaError> {
+        match self.json()? {
+            Value::String(s) => Ok(s),
+            _ => Err(self.error("expected string")),
+        }
+    }
+
+    fn annotations(&mut self) -> Result<Map<String, Value>, SchemaError> {
+        let mut annotations = Map::new();
+        while self.eat('@')? {
+            let rest = self.rest();
+            let len = rest
+                .find(|c| !(is_identifier_char(c) || c == '-'))
+                .unwrap_or(rest.len());
+            self.position += len;
+            self.expect('(')?;
+            let value = self.json()?;
+            self.expect(')')?;
+            annotations.insert(rest[..len].to_string(), value);
+        }
+        Ok(annotations)
+    }
+
+    fn parse(mut self) -> Result<Protocol, SchemaError> {
+        let mut protocol = Protocol::default();
+        self.skip_whitespace()?;
+        let doc = self.doc.take();
+        let mut annotations = self.annotations()?;
+        if self.keyword("protocol")? {
+            let mut name = Name::new(&self.identifier()?);
+            if let Some(namespace) = annotations.remove("namespace") {
+                name.namespace = namespace.as_str().map(String::from);
+            }
+            self.namespace = name.namespace.clone();
+            protocol.name = Some(name);
+            protocol.doc = doc;
+            protocol.attributes = annotations;
+            self.expect('{')?;
+            while !self.eat('}')? {
+                self.declaration(&mut protocol, true)?;
+            }
+        } else {
+            if !annotations.is_empty() {
+                return Err(self.error("expected protocol"));
+            }
+            if self.keyword("namespace")? {
+                self.namespace = Some(self.identifier()?);
+                self.expect(';')?;
+            }
+            if self.keyword("schema")? {
+                protocol.schema = Some(self.schema()?);
+                self.expect(';')?;
+            }
+            while {
+                self.skip_whitespace()?;
+                !self.rest().is_empty()
+            } {
+                self.declaration(&mut protocol, false)?;
+            }
+        }
+        self.skip_whitespace()?;
+        if !self.rest().is_empty() {
+            return Err(self.error("unexpected content after protocol"));
+        }
+        Ok(protocol)
+    }
+
+    fn declaration(&mut self, protocol: &mut Protocol, messages: bool) -> Result<(), SchemaError> {
+        self.skip_whitespace()?;
+        let doc = self.doc.take();
+        let annotations = self.annotations()?;
+        let doc = doc.or_else(|| self.doc.take());
+        if self.keyword("import")? {
+            return self.import(protocol);
+        }
+        let schema = if self.keyword("record")? {
+            self.record("record", doc, annotations)?
+        } else if self.keyword("error")? {
+            self.record("error", doc, annotations)?
+        } else if self.keyword("enum")? {
+            self.enumeration(doc, annotations)?
+        } else if self.keyword("fixed")? {
+            self.fixed(doc, annotations)?
+        } else if messages {
+            let (name, message) = self.message(doc, annotations)?;
+            protocol.messages.insert(name, message);
+            return Ok(());
+        } else {
+            return Err(self.error("expected named type declaration"));
+        };
+        protocol.types.push(schema);
+        Ok(())
+    }
+
+    fn import(&mut self, protocol: &mut Protocol) -> Result<(), SchemaError> {
+        let kind = self.identifier()?;
+        if !matches!(kind.as_str(), "idl" | "protocol" | "schema") {
+            return Err(self.error(format!("unknown import kind {kind}")));
+        }
+        let file = self.string()?;
+        let path = self.directory.join(file);
+        self.expect(';')?;
+        if !self.imported.insert(canonical(&path)) {
+            return Ok(());
+        }
+        let input = read(&path)?;
+        match kind.as_str() {
+            "idl" => {
+                let imported = Parser::new(&input, directory(&path), self.imported).parse()?;
+                protocol.types.extend(imported.types);
+                protocol.messages.extend(imported.messages);
+            }
+            "protocol" => {
+                let mut imported: Value = serde_json::from_str(&input)?;
+                let namespace = imported.get("namespace").cloned();
+                if let Some(Value::Array(types)) = imported.get_mut("types").map(Value::take) {
+                    protocol.types.extend(types.into_iter().map(|mut schema| {
+                        if let (Some(object), Some(namespace)) =
+                            (schema.as_object_mut(), &namespace)
+                        {
+                            object
+                                .entry("namespace")
+                                .or_insert_with(|| namespace.clone());
+                        }
+                        schema
+                    }));
+                }
+                if let Some(Value::Object(messages)) = imported.get_mut("messages").map(Value::take)
+                {
+                    protocol.messages.extend(messages);
+                }
+            }
+            _ => protocol.types.push(serde_json::from_str(&input)?),
+        }
+        Ok(())
+    }
+
+    fn named(
+        &mut self,
+        r#type: &str,
+        doc: Option<String>,
+        annotations: Map<String, Value>,
+    ) -> Result<Map<String, Value>, SchemaError> {
+        let mut schema = Map::new();
+        schema.insert("type".into(), r#type.into());
+        schema.insert("name".into(), self.identifier()?.into());
+        if let Some(namespace) = &self.namespace {
+            schema.insert("namespace".into(), namespace.clone().into());
+        }
+        if let Some(doc) = doc {
+            schema.insert("doc".into(), doc.into());
+        }
+        schema.extend(annotations);
+        Ok(schema)
+    }
+
+    fn record(
+        &mut self,
+        r#type: &str,
+        doc: Option<String>,
+        annotations: Map<String, Value>,
+    ) -> Result<Value, SchemaError> {
+        let mut schema = self.named(r#type, doc, annotations)?;
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        while !self.eat('}')? {
+            self.skip_whitespace()?;
+            let doc = self.doc.take();
+            let (r#type, nullable) = self.field_type()?;
+            loop {
+                self.skip_whitespace()?;
+                let variable_doc = self.doc.take();
+                let mut field = Map::new();
+                let annotations = self.annotations()?;
+                field.insert("name".into(), self.identifier()?.into());
+                let mut r#type = r#type.clone();
+                if self.eat('=')? {
+                    let default = self.json()?;
+                    // The default value of a union must match its first branch.
+                    if nullable && !default.is_null() {
+                        r#type = json!([r#type[1], "null"]);
+                    }
+                    field.insert("default".into(), default);
+                }
+                field.insert("type".into(), r#type);
+                if let Some(doc) = variable_doc.or_else(|| doc.clone()) {
+                    field.insert("doc".into(), doc.into());
+                }
+                field.extend(annotations);
+                fields.push(Value::Object(field));
+                if !self.eat(',')? {
+                    break;
+                }
+            }
+            self.expect(';')?;
+        }
+        schema.insert("fields".into(), fields.into());
+        Ok(Value::Object(schema))
+    }
+
+    fn enumeration(
+        &mut self,
+        doc: Option<String>,
+        annotations: Map<String, Value>,
+    ) -> Result<Value, SchemaError> {
+        let mut schema = self.named("enum", doc, annotations)?;
+        self.expect('{')?;
+        let mut symbols = Vec::new();
+        if !self.eat('}')? {
+            loop {
+                symbols.push(Value::String(self.identifier()?));
+                if !self.eat(',')? {
+                    break;
+                }
+            }
+            self.expect('}')?;
+        }
+        schema.insert("symbols".into(), symbols.into());
+        if self.eat('=')? {
+            schema.insert("default".into(), self.identifier()?.into());
+            self.expect(';')?;
+        }
+        Ok(Value::Object(schema))
+    }
+
+    fn fixed(
+        &mut self,
+        doc: Option<String>,
+        annotations: Map<String, Value>,
+    ) -> Result<Value, SchemaError> {
+        let mut schema = self.named("fixed", doc, annotations)?;
+        self.expect('(')?;
+        let size = self.json()?;
+        if !size.is_u64() {
+            return Err(self.error("expected fixed size"));
+        }
+        schema.insert("size".into(), size);
+        self.expect(')')?;
+        self.expect(';')?;
+        Ok(Value::Object(schema))
+    }
+
+    fn message(
+        &mut self,
+        doc: Option<String>,
+        annotations: Map<String, Value>,
+    ) -> Result<(String, Value), SchemaError> {
+        let response = if self.keyword("void")? {
+            "null".into()
+        } else {
+            self.schema()?
+        };
+        let name = self.identifier()?;
+        let mut message = Map::new();
+        if let Some(doc) = doc {
+            message.insert("doc".into(), doc.into());
+        }
+        message.extend(annotations);
+        let mut request = Vec::new();
+        self.expect('(')?;
+        if !self.eat(')')? {
+            loop {
+                let mut parameter = Map::new();
+                let annotations = self.annotations()?;
+                let r#type = self.schema()?;
+                parameter.insert("name".into(), self.identifier()?.into());
+                parameter.insert("type".into(), r#type);
+                if self.eat('=')? {
+                    parameter.insert("default".into(), self.json()?);
+                }
+                parameter.extend(annotations);
+                request.push(Value::Object(parameter));
+                if !self.eat(',')? {
+                    break;
+                }
+            }
+            self.expect(')')?;
+        }
+        message.insert("request".into(), request.into());
+        message.insert("response".into(), response);
+        if self.keyword("oneway")? {
+            message.insert("one-way".into(), true.into());
+        } else if self.keyword("throws")? {
+            let mut errors = Vec::new();
+            loop {
+                let error = self.identifier()?;
+                errors.push(self.reference(error));
+                if !self.eat(',')? {
+                    break;
+                }
+            }
+            message.insert("errors".into(), errors.into());
+        }
+        self.expect(';')?;
+        Ok((name, Value::Object(message)))
+    }
+
+    fn schema(&mut self) -> Result<Value, SchemaError> {
+        Ok(self.field_type()?.0)
+    }
+
+    /// References are relative to the namespace of the file.
+    fn reference(&self, name: String) -> Value {
+        match &self.namespace {
+            Some(namespace) if !name.contains('.') => format!("{namespace}.{name}").into(),
+            _ => name.into(),
+        }
+    }
+
+    /// Type, which is a union with `null` first when followed by `?`, and whether it is.
+    fn field_type(&mut self) -> Result<(Value, bool), SchemaError> {
+        let annotations = self.annotations()?;
+        let schema = if self.keyword("array")? {
+            self.expect('<')?;
+            let items = self.schema()?;
+            self.expect('>')?;
+            json!({"type": "array", "items": items})
+        } else if self.keyword("map")? {
+            self.expect('<')?;
+            let values = self.schema()?;
+            self.expect('>')?;
+            json!({"type": "map", "values": values})
+        } else if self.keyword("union")? {
+            self.expect('{')?;
+            let mut schemas = Vec::new();
+            loop {
+                schemas.push(self.schema()?);
+                if !self.eat(',')? {
+                    break;
+                }
+            }
+            self.expect('}')?;
+            Value::Array(schemas)
+        } else if self.keyword("decimal")? {
+            self.expect('(')?;
+            let precision = self.json()?;
+            self.expect(',')?;
+            let scale = self.json()?;
+            self.expect(')')?;
+            json!({"type": "bytes", "logicalType": "decimal", "precision": precision, "scale": scale})
+        } else {
+            let name = self.identifier()?;
+            match name.as_str() {
+                "date" => json!({"type": "int", "logicalType": "date"}),
+                "time_ms" => json!({"type": "int", "logicalType": "time-millis"}),
+                "timestamp_ms" => json!({"type": "long", "logicalType": "timestamp-millis"}),
+                "local_timestamp_ms" => {
+                    json!({"type": "long", "logicalType": "local-timestamp-millis"})
+                }
+                "uuid" => json!({"type": "string", "logicalType": "uuid"}),
+                "null" | "boolean" | "int" | "long" | "float" | "double" | "bytes" | "string" => {
+                    Value::String(name)
+                }
+                _ => self.reference(name),
+            }
+        };
+        let schema = match (schema, annotations.is_empty()) {
+            (schema, true) => schema,
+            (Value::Object(mut schema), false) => {
+                schema.extend(annotations);
+                Value::Object(schema)
+            }
+            (Value::String(name), false) => {
+                let mut schema = Map::from_iter([("type".to_string(), Value::String(name))]);
+                schema.extend(annotations);
+                Value::Object(schema)
+            }
+            _ => return Err(self.error("unions cannot be annotated")),
+        };
+        if self.eat('?')? {
+            return Ok((json!(["null", schema]), true));
+        }
+        Ok((schema, false))
+    }
+}
+
+/// Remove the leading `*` of the lines of a documentation comment.
+fn doc_comment(doc: &str) -> String {
+    doc.lines()
+        .map(|line| {
+            let line = line.trim();
+            line.strip_prefix('*').map_or(line, str::trim)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
@@ -0,0 +1,1365 @@
+//! [Avro JSON encoding](https://avro.apache.org/docs/current/specification/#json-encoding),
+//! walking the same compiled schema as the binary serializer: non-null union branches are
+//! wrapped in an object keyed by the branch name, bytes and fixed are ISO-8859-1 strings, and
+//! logical types are encoded as their underlying type.
+
+use serde::{
+    de::{self, value::BorrowedStrDeserializer, DeserializeSeed, IntoDeserializer, Visitor},
+    ser, Serialize,
+};
+use serde_json::{Map, Value};
+
+use crate::{
+    config::{SerializerConfig, VariantRepresentation},
+    error::SerializationError,
+    logical,
+    node::{Field, Node, NodeId},
+    schema::SerializationSchemaKind,
+    ser::{
+        record_branch, tag_fields, unit_variant_branch, variant_branch, Content, ContentSerializer,
+        Tagged, WithPath,
+    },
+    utils::{FieldChecker, StringChecker},
+    value::UNION_BRANCH,
+};
+
+/// Turns a value into the JSON of the selected union branch.
+type Wrap = Box<dyn FnOnce(Value) -> Value>;
+
+fn unwrapped() -> Wrap {
+    Box::new(|value| value)
+}
+
+/// Name of the union branch, i.e. the full name of named types and the type name otherwise.
+pub(crate) fn branch_name(schema: &Node, nodes: &[Node]) -> String {
+    match schema {
+        Node::Decimal { inner, .. } | Node::Duration { inner } => {
+            branch_name(&nodes[*inner as usize], nodes)
+        }
+        Node::Uuid => "string".into(),
+        Node::Date | Node::TimeMillis => "int".into(),
+        Node::TimeMicros | Node::TimestampMillis | Node::TimestampMicros => "long".into(),
+        _ => match schema.name() {
+            Some(name) => name.to_string(),
+            None => <&str>::from(SerializationSchemaKind::from(schema)).to_lowercase(),
+        },
+    }
+}
+
+fn wrap_branch(branch: &Node, nodes: &[Node]) -> Wrap {
+    match branch {
+        Node::Null => unwrapped(),
+        _ => {
+            let name = branch_name(branch, nodes);
+            Box::new(move |value| Value::Object(Map::from_iter([(name, value)])))
+        }
+    }
+}
+
+/// Bytes as ISO-8859-1 string, i.e. one code point per byte.
+pub(crate) fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+fn encode_bytes(bytes: &[u8]) -> Value {
+    Value::String(latin1(bytes))
+}
+
+pub(crate) fn decode_bytes(value: &Value) -> Result<Vec<u8>, SerializationError> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("expected bytes, found {value}"))?
+        .chars()
+        .map(|c| u8::try_from(c).map_err(|_| format!("invalid byte {c:?}").into()))
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct JsonSerializer<'a> {
+    pub(crate) schema: &'a Node,
+    pub(crate) nodes: &'a [Node],
+    pub(crate) config: &'a SerializerConfig,
+}
+
+impl<'a> JsonSerializer<'a> {
+    fn with_schema(self, schema: &'a Node) -> Self {
+        Self { schema, ..self }
+    }
+
+    fn with_node(self, id: NodeId) -> Self {
+        self.with_schema(self.node(id))
+    }
+
+    fn node(&self, id: NodeId) -> &'a Node {
+        &self.nodes[id as usize]
+    }
+
+    /// Select the schema, or the union branch, of the first of the expected kinds.
+    fn select(
+        &self,
+        expected: &[SerializationSchemaKind],
+    ) -> Result<(&'a Node, Wrap), SerializationError> {
+        let schema = self.schema;
+        if expected.contains(&SerializationSchemaKind::from(schema)) {
+            return Ok((schema, unwrapped()));
+        }
+        let branches = match schema {
+            Node::Union {
+                branches,
+                variant_index,
+            } => {
+                if let Some(index) = expected.iter().find_map(|kind| variant_index.get(kind)) {
+                    let branch = self.node(branches[*index]);
+                    return Ok((branch, wrap_branch(branch, self.nodes)));
+                }
+                branches
+            }
+            Node::Nullable { branches, .. } => branches,
+            _ => &[][..],
+        };
+        // Named types sharing their kind with other branches are not in `variant_index`, and
+        // the branches of nullable unions are not indexed.
+        for kind in expected {
+            if let Some(branch) = branches
+                .iter()
+                .map(|id| self.node(*id))
+                .find(|s| SerializationSchemaKind::from(*s) == *kind)
+            {
+                return Ok((branch, wrap_branch(branch, self.nodes)));
+            }
+        }
+        Err(SerializationError::SchemaMismatch {
+            expected: Box::new(self.schema.to_schema(self.nodes)),
+            found: expected[0],
+            path: Default::default(),
+        })
+    }
+
+    fn record_fields(&self) -> Result<(&'a [Field], Wrap), SerializationError> {
+        match self.select(&[SerializationSchemaKind::Record])? {
+            (Node::Record { fields, .. }, wrap) => Ok((fields, wrap)),
+            _ => unreachable!(),
+        }
+    }
+
+    fn primitive(
+        self,
+        value: impl Into<Value>,
+        expected: &[SerializationSchemaKind],
+    ) -> Result<Value, SerializationError> {
+        let (_, wrap) = self.select(expected)?;
+        Ok(wrap(value.into()))
+    }
+
+    /// Select the union branch named after the variant, see [`variant_branch`].
+    fn branch(&self, branches: &'a [NodeId], variant: &'static str) -> Option<&'a Node> {
+        variant_branch(self.nodes, branches, variant).map(|(_, branch)| branch)
+    }
+
+    /// The record holding the tag, and where the content of the variant goes.
+    fn tag(
+        self,
+        fields: &'a [Field],
+        variant: &'static str,
+    ) -> Result<(Map<String, Value>, Tagged<'a>), SerializationError> {
+        let (tag, tagged) = tag_fields(self.config, fields)?;
+        let tag_value = variant.serialize(self.with_node(tag.node))?;
+        Ok((Map::from_iter([(tag.name.clone(), tag_value)]), tagged))
+    }
+
+    /// Two's complement bytes of a decimal, sign extended for a fixed.
+    fn decimal(self, bytes: &[u8]) -> Result<Value, SerializationError> {
+        match self.schema {
+            Node::Fixed { size, .. } => {
+                let bytes = logical::sign_extend(bytes, *size)
+                    .ok_or_else(|| format!("decimal doesn't fit in fixed {size}"))?;
+                Ok(encode_bytes(&bytes))
+            }
+            _ => Ok(encode_bytes(bytes)),
+        }
+    }
+
+    fn collection(self, wrap: Wrap) -> JsonCollection<'a> {
+        JsonCollection {
+            serializer: self,
+            values: Vec::new(),
+            map: Map::new(),
+            key: None,
+            fields: None,
+            wrap,
+        }
+    }
+
+    fn record(
+        self,
+        name: &'static str,
+        fields: &'a [Field],
+        map: Map<String, Value>,
+        wrap: Wrap,
+    ) -> JsonRecord<'a> {
+        JsonRecord {
+            serializer: self,
+            type_name: name,
+            fields,
+            next: 0,
+            pending: Vec::new(),
+            map,
+            wrap,
+        }
+    }
+}
+
+/// Wraps the content of an adjacently tagged variant into its tag record.
+fn wrap_content(mut map: Map<String, Value>, content: String, wrap: Wrap) -> Wrap {
+    Box::new(move |value| {
+        map.insert(content, value);
+        wrap(Value::Object(map))
+    })
+}
+
+pub(crate) struct JsonCollection<'a> {
+    /// Serializer of the items.
+    serializer: JsonSerializer<'a>,
+    values: Vec<Value>,
+    map: Map<String, Value>,
+    key: Option<String>,
+    /// Remaining fields of a record serialized from map entries.
+    fields: Option<&'a [Field]>,
+    wrap: Wrap,
+}
+
+impl ser::SerializeSeq for JsonCollection<'_> {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.values.push(value.serialize(self.serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok((self.wrap)(Value::Array(self.values)))
+    }
+}
+
+impl ser::SerializeTuple for JsonCollection<'_> {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for JsonCollection<'_> {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for JsonCollection<'_> {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeMap for JsonCollection<'_> {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        if let Some(fields) = self.fields {
+            let field = fields.first().ok_or("unexpected field")?;
+            key.serialize(FieldChecker(&field.name))?;
+            self.key = Some(field.name.clone());
+            return Ok(());
+        }
+        key.serialize(StringChecker)?;
+        let Value::String(key) = key.serialize(self.serializer.with_schema(&Node::String))? else {
+            return Err("map key is not a string".into());
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self.key.take().ok_or("map value without key")?;
+        let serializer = match &mut self.fields {
+            Some(fields) => {
+                let (field, remaining) = fields.split_first().ok_or("unexpected field")?;
+                *fields = remaining;
+                self.serializer.with_node(field.node)
+            }
+            None => self.serializer,
+        };
+        self.map.insert(key, value.serialize(serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        if let Some([field, ..]) = self.fields {
+            return Err(format!("missing field {}", field.name).into());
+        }
+        Ok((self.wrap)(Value::Object(self.map)))
+    }
+}
+
+pub(crate) struct JsonRecord<'a> {
+    serializer: JsonSerializer<'a>,
+    type_name: &'static str,
+    fields: &'a [Field],
+    /// Index of the next field to insert.
+    next: usize,
+    /// Fields serialized before fields preceding them, with their field index, kept until they
+    /// are inserted in field order.
+    pending: Vec<(usize, Value)>,
+    map: Map<String, Value>,
+    wrap: Wrap,
+}
+
+impl JsonRecord<'_> {
+    fn field_index(&self, key: &str) -> Option<usize> {
+        if self
+            .fields
+            .get(self.next)
+            .is_some_and(|field| field.name == key)
+        {
+            return Some(self.next);
+        }
+        self.fields.iter().position(|field| field.name == key)
+    }
+
+    /// Insert the pending fields following the inserted ones.
+    fn insert_pending(&mut self) {
+        while let Some(position) = self
+            .pending
+            .iter()
+            .position(|(index, _)| *index == self.next)
+        {
+            let (_, value) = self.pending.swap_remove(position);
+            self.map.insert(self.fields[self.next].name.clone(), value);
+            self.next += 1;
+        }
+    }
+}
+
+impl ser::SerializeStruct for JsonRecord<'_> {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        let index = match self.field_index(key) {
+            Some(index) if index == self.next => index,
+            Some(index) if index > self.next && self.pending.iter().all(|(i, _)| *i != index) => {
+                index
+            }
+            Some(_) => {
+                return Err(format!("duplicate field {key}").into()).with_path(self.type_name, key)
+            }
+            None => return Err("unexpected field".into()).with_path(self.type_name, key),
+        };
+        let value = value
+            .serialize(self.serializer.with_node(self.fields[index].node))
+            .with_path(self.type_name, key)?;
+        if index > self.next {
+            self.pending.push((index, value));
+            return Ok(());
+        }
+        self.map.insert(key.into(), value);
+        self.next += 1;
+        self.insert_pending();
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        if let Some(field) = self.fields.get(self.next) {
+            return Err(format!("missing field {}", field.name).into())
+                .with_path(self.type_name, "");
+        }
+        Ok((self.wrap)(Value::Object(self.map)))
+    }
+}
+
+impl ser::SerializeStructVariant for JsonRecord<'_> {
+    type Ok = Value;
+    type Error = SerializationError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeStruct::end(self)
+    }
+}
+
+impl<'a> serde::Serializer for JsonSerializer<'a> {
+    type Ok = Value;
+    type Error = SerializationError;
+    type SerializeSeq = JsonCollection<'a>;
+    type SerializeTuple = JsonCollection<'a>;
+    type SerializeTupleStruct = JsonCollection<'a>;
+    type SerializeTupleVariant = JsonCollection<'a>;
+    type SerializeMap = JsonCollection<'a>;
+    type SerializeStruct = JsonRecord<'a>;
+    type SerializeStructVariant = JsonRecord<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.primitive(v, &[SerializationSchemaKind::Boolean])
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.primitive(
+            v,
+            &[SerializationSchemaKind::Int, SerializationSchemaKind::Long],
+        )
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.primitive(
+            v,
+            &[SerializationSchemaKind::Int, SerializationSchemaKind::Long],
+        )
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        use SerializationSchemaKind::*;
+        self.primitive(
+            v,
+            &[
+                Int,
+                Long,
+                Date,
+                TimeMillis,
+                TimeMicros,
+                TimestampMillis,
+                TimestampMicros,
+            ],
+        )
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        use SerializationSchemaKind::*;
+        self.primitive(v, &[Long, TimeMicros, TimestampMillis, TimestampMicros])
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.primitive(
+            v,
+            &[SerializationSchemaKind::Int, SerializationSchemaKind::Long],
+        )
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.primitive(
+            v,
+            &[SerializationSchemaKind::Int, SerializationSchemaKind::Long],
+        )
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.primitive(
+            v,
+            &[SerializationSchemaKind::Int, SerializationSchemaKind::Long],
+        )
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.primitive(v, &[SerializationSchemaKind::Long])
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.primitive(v, &[SerializationSchemaKind::Float])
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.primitive(v, &[SerializationSchemaKind::Double])
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.primitive(v.to_string(), &[SerializationSchemaKind::String])
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        use SerializationSchemaKind::*;
+        let invalid = |kind| format!("invalid {kind} {v}");
+        let (schema, wrap) = self.select(&[
+            String,
+            Uuid,
+            Enum,
+            Date,
+            TimeMillis,
+            TimeMicros,
+            TimestampMillis,
+            TimestampMicros,
+            Decimal,
+        ])?;
+        let value = match schema {
+            Node::Enum { symbols, .. } => {
+                if !symbols.contains(v) {
+                    return Err(format!("unexpected {v} in enum").into());
+                }
+                v.into()
+            }
+            Node::Date => logical::parse_date(v)
+                .ok_or_else(|| invalid("date"))?
+                .into(),
+            Node::TimeMillis => {
+                let nanos = logical::parse_time(v).ok_or_else(|| invalid("time"))?;
+                ((nanos / 1_000_000) as i32).into()
+            }
+            Node::TimeMicros => {
+                let nanos = logical::parse_time(v).ok_or_else(|| invalid("time"))?;
+                (nanos / 1_000).into()
+            }
+            Node::TimestampMillis => {
+                let nanos = logical::parse_timestamp(v).ok_or_else(|| invalid("timestamp"))?;
+                (nanos.div_euclid(1_000_000) as i64).into()
+            }
+            Node::TimestampMicros => {
+                let nanos = logical::parse_timestamp(v).ok_or_else(|| invalid("timestamp"))?;
+                (nanos.div_euclid(1_000) as i64).into()
+            }
+            Node::Decimal {
+                precision,
+                scale,
+                inner,
+            } => {
+                let bytes = logical::decimal_bytes(v, *precision, *scale)
+                    .ok_or_else(|| invalid("decimal"))?;
+                return self.with_node(*inner).decimal(&bytes).map(wrap);
+            }
+            _ => v.into(),
+        };
+        Ok(wrap(value))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        use SerializationSchemaKind::*;
+        let (schema, wrap) = self.select(&[Bytes, Uuid, Fixed, Decimal, Duration])?;
+        match schema {
+            Node::Fixed { size, .. } if *size != v.len() => {
+                return Err(format!("expected fixed {size}, found {}", v.len()).into());
+            }
+            Node::Decimal { inner, .. } => {
+                return self.with_node(*inner).decimal(v).map(wrap);
+            }
+            Node::Duration { .. } if v.len() != 12 => {
+                return Err(format!("expected duration of 12 bytes, found {}", v.len()).into());
+            }
+            _ => {}
+        }
+        Ok(wrap(encode_bytes(v)))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.primitive(Value::Null, &[SerializationSchemaKind::Null])
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        // As in binary, the value of an option in a nullable union can't be null itself.
+        if let Node::Nullable {
+            branches,
+            null_index,
+        } = self.schema
+        {
+            let branch = self.node(branches[1 - null_index]);
+            return value
+                .serialize(self.with_schema(branch))
+                .map(wrap_branch(branch, self.nodes));
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.primitive(Value::Null, &[SerializationSchemaKind::Null])
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.primitive(Value::Null, &[SerializationSchemaKind::Null])
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        match self.schema {
+            Node::Null => return Ok(Value::Null),
+            Node::Union { branches, .. } | Node::Nullable { branches, .. } => {
+                match unit_variant_branch(self.nodes, branches, variant_index, variant)
+                    .with_path(name, variant)?
+                {
+                    Some((index, Some(_))) => {
+                        let branch = self.node(branches[index]);
+                        return Ok(wrap_branch(branch, self.nodes)(variant.into()));
+                    }
+                    Some((_, None)) => return Ok(Value::Null),
+                    None => {}
+                }
+            }
+            Node::Record { fields, .. } => {
+                return match self.tag(fields, variant).with_path(name, variant)? {
+                    (map, Tagged::Content(content)) => {
+                        ().serialize(self.with_node(content.node)).map(wrap_content(
+                            map,
+                            content.name.clone(),
+                            unwrapped(),
+                        ))
+                    }
+                    (map, Tagged::Fields(fields)) => ContentSerializer(JsonContent {
+                        serializer: self,
+                        fields,
+                        map,
+                        wrap: unwrapped(),
+                    })
+                    .serialize_unit(),
+                }
+                .with_path(name, variant);
+            }
+            _ => {}
+        }
+        variant.serialize(self).with_path(name, variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self).with_path(name, "")
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        if name == UNION_BRANCH {
+            let Some(branches) = self.schema.branches() else {
+                return value.serialize(self);
+            };
+            let branch = branches
+                .get(variant_index as usize)
+                .ok_or_else(|| format!("invalid union branch {variant_index}"))?;
+            let branch = self.node(*branch);
+            return value
+                .serialize(self.with_schema(branch))
+                .map(wrap_branch(branch, self.nodes));
+        }
+        if let Some(branches) = self.schema.branches() {
+            if let Some(branch) = self.branch(branches, variant) {
+                return value
+                    .serialize(self.with_schema(branch))
+                    .map(wrap_branch(branch, self.nodes))
+                    .with_path(name, variant);
+            }
+        }
+        let (fields, wrap) = self.record_fields().with_path(name, variant)?;
+        match self.tag(fields, variant).with_path(name, variant)? {
+            (map, Tagged::Content(content)) => value
+                .serialize(self.with_node(content.node))
+                .map(wrap_content(map, content.name.clone(), wrap)),
+            (map, Tagged::Fields(fields)) => value.serialize(ContentSerializer(JsonContent {
+                serializer: self,
+                fields,
+                map,
+                wrap,
+            })),
+        }
+        .with_path(name, variant)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        match self.select(&[SerializationSchemaKind::Array])? {
+            (Node::Array(items), wrap) => Ok(self.with_node(*items).collection(wrap)),
+            _ => unreachable!(),
+        }
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len)).with_path(name, "")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        let (fields, wrap) = self.record_fields().with_path(name, variant)?;
+        let (map, Tagged::Content(content)) = self.tag(fields, variant).with_path(name, variant)?
+        else {
+            return Err("internally tagged tuple variant is not supported".into())
+                .with_path(name, variant);
+        };
+        let mut collection = self
+            .with_node(content.node)
+            .serialize_seq(Some(len))
+            .with_path(name, variant)?;
+        let (inner, outer) = (
+            collection.wrap,
+            wrap_content(map, content.name.clone(), wrap),
+        );
+        collection.wrap = Box::new(move |value| outer(inner(value)));
+        Ok(collection)
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        match self.select(&[
+            SerializationSchemaKind::Map,
+            SerializationSchemaKind::Record,
+        ])? {
+            (Node::Map(values), wrap) => Ok(self.with_node(*values).collection(wrap)),
+            (Node::Record { fields, .. }, wrap) => Ok(JsonCollection {
+                fields: Some(fields),
+                ..self.collection(wrap)
+            }),
+            _ => unreachable!(),
+        }
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        if let Node::Union {
+            branches,
+            variant_index,
+            ..
+        } = self.schema
+        {
+            if !variant_index.contains_key(&SerializationSchemaKind::Record) {
+                let branches = branches.iter().map(|id| self.node(*id));
+                if let Some((_, schema)) = record_branch(branches, name).with_path(name, "")? {
+                    let Node::Record { fields, .. } = schema else {
+                        unreachable!()
+                    };
+                    let wrap = wrap_branch(schema, self.nodes);
+                    return Ok(self.record(name, fields, Map::new(), wrap));
+                }
+            }
+        }
+        let (fields, wrap) = self.record_fields().with_path(name, "")?;
+        Ok(self.record(name, fields, Map::new(), wrap))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        if let Some(branches) = self.schema.branches() {
+            if let Some(branch) = self.branch(branches, variant) {
+                let (fields, _) = self
+                    .with_schema(branch)
+                    .record_fields()
+                    .with_path(name, variant)?;
+                let wrap = wrap_branch(branch, self.nodes);
+                return Ok(self.record(name, fields, Map::new(), wrap));
+            }
+        }
+        let (fields, wrap) = self.record_fields().with_path(name, variant)?;
+        match self.tag(fields, variant).with_path(name, variant)? {
+            (map, Tagged::Content(content)) => {
+                let (fields, inner) = self
+                    .with_node(content.node)
+                    .record_fields()
+                    .with_path(name, variant)?;
+                let wrap = wrap_content(map, content.name.clone(), wrap);
+                Ok(self.record(name, fields, Map::new(), Box::new(move |v| wrap(inner(v)))))
+            }
+            (map, Tagged::Fields(fields)) => Ok(self.record(name, fields, map, wrap)),
+        }
+    }
+}
+
+/// Fields following the tag of an internally tagged variant, see [`ContentSerializer`].
+struct JsonContent<'a> {
+    serializer: JsonSerializer<'a>,
+    fields: &'a [Field],
+    map: Map<String, Value>,
+    wrap: Wrap,
+}
+
+impl<'a> Content for JsonContent<'a> {
+    type Ok = Value;
+    type Record = JsonRecord<'a>;
+
+    fn fields(&self) -> &[Field] {
+        self.fields
+    }
+
+    fn record(self, name: &'static str) -> Self::Record {
+        self.serializer
+            .record(name, self.fields, self.map, self.wrap)
+    }
+
+    fn unit(self) -> Result<Value, SerializationError> {
+        Ok((self.wrap)(Value::Object(self.map)))
+    }
+}
+
+/// Reads a value from its Avro JSON encoding. Logical types are read as strings (e.g. RFC 3339
+/// timestamps or decimal strings), unless an integer or bytes are requested.
+#[derive(Clone, Copy)]
+pub(crate) struct JsonDeserializer<'a, 'de> {
+    pub(crate) value: &'de Value,
+    pub(crate) schema: &'a Node,
+    pub(crate) nodes: &'a [Node],
+    pub(crate) config: &'a SerializerConfig,
+}
+
+/// Content of a variant: a value or, for internally tagged variants, the fields following the tag.
+enum VariantContent<'a, 'de> {
+    Unit,
+    Value(JsonDeserializer<'a, 'de>),
+    Fields(JsonFields<'a, 'de>),
+}
+
+impl<'a, 'de> JsonDeserializer<'a, 'de> {
+    fn with(self, value: &'de Value, schema: &'a Node) -> Self {
+        Self {
+            value,
+            schema,
+            ..self
+        }
+    }
+
+    fn node(&self, id: NodeId) -> &'a Node {
+        &self.nodes[id as usize]
+    }
+
+    fn mismatch(&self) -> SerializationError {
+        format!(
+            "expected {}, found {}",
+            branch_name(self.schema, self.nodes),
+            self.value
+        )
+        .into()
+    }
+
+    /// The union branch the value is encoded as, or the schema itself if not a union.
+    fn branch(self) -> Result<(Self, Option<&'a Node>), SerializationError> {
+        let Some(branches) = self.schema.branches() else {
+            return Ok((self, None));
+        };
+        let mut branches = branches.iter().map(|id| self.node(*id));
+        if self.value.is_null() {
+            if let Some(branch) = branches.clone().find(|s| matches!(s, Node::Null)) {
+                return Ok((self.with(self.value, branch), Some(branch)));
+            }
+        }
+        let Some((name, value)) = self
+            .value
+            .as_object()
+            .filter(|object| object.len() == 1)
+            .and_then(|object| object.iter().next())
+        else {
+            return Err(format!("expected union branch, found {}", self.value).into());
+        };
+        let branch = branches
+            .find(|s| branch_name(s, self.nodes) == *name || s.is_named(name))
+            .ok_or_else(|| format!("unknown union branch {name}"))?;
+        Ok((self.with(value, branch), Some(branch)))
+    }
+
+    fn integer(&self) -> Result<i64, SerializationError> {
+        self.value.as_i64().ok_or_else(|| self.mismatch())
+    }
+
+    fn string(&self) -> Result<&'de str, SerializationError> {
+        self.value.as_str().ok_or_else(|| self.mismatch())
+    }
+
+    /// Variant named after the union branch, either by named type or by kind.
+    fn variant(
+        self,
+        variants: &'static [&'static str],
+    ) -> Result<(&'de str, VariantContent<'a, 'de>), SerializationError> {
+        let (this, branch) = self.branch()?;
+        let Some(branch) = branch else {
+            let Node::Record { fields, .. } = this.schema else {
+                if let Node::Enum { .. } = this.schema {
+                    return Ok((this.string()?, VariantContent::Unit));
+                }
+                return Err(this.mismatch());
+            };
+            return this.tagged(fields);
+        };
+        if let Node::Null = branch {
+            let variant = variants
+                .iter()
+                .find(|v| v.eq_ignore_ascii_case("null"))
+                .ok_or("null union branch without Null variant")?;
+            return Ok((variant, VariantContent::Unit));
+        }
+        if let (Node::Enum { .. }, Value::String(symbol)) = (this.schema, this.value) {
+            if variants.contains(&symbol.as_str()) {
+                return Ok((symbol, VariantContent::Unit));
+            }
+        }
+        let kind = <&str>::from(SerializationSchemaKind::from(this.schema));
+        if let Some(variant) = variants
+            .iter()
+            .find(|v| this.schema.is_named(v) || kind == **v)
+        {
+            return Ok((variant, VariantContent::Value(this)));
+        }
+        match this.schema {
+            Node::Record { fields, .. } => this.tagged(fields),
+            _ => {
+                let branch = branch_name(branch, this.nodes);
+                Err(format!("no variant for union branch {branch}").into())
+            }
+        }
+    }
+
+    /// Variant of a tag record.
+    fn tagged(
+        self,
+        fields: &'a [Field],
+    ) -> Result<(&'de str, VariantContent<'a, 'de>), SerializationError> {
+        let object = self.value.as_object().ok_or_else(|| self.mismatch())?;
+        let tag = match &self.config.variant_representation {
+            VariantRepresentation::AdjacentlyTagged { tag, .. }
+            | VariantRepresentation::InternallyTagged { tag } => tag,
+            VariantRepresentation::Union => {
+                return Err("variant must be deserialized from union branch".into())
+            }
+        };
+        if fields.first().is_none_or(|field| field.name != *tag) {
+            return Err(format!("tag record must have \"{tag}\" as first field").into());
+        }
+        let variant = object
+            .get(tag)
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("missing tag {tag}"))?;
+        let content = match &self.config.variant_representation {
+            VariantRepresentation::AdjacentlyTagged { content, .. } => {
+                let schema = self.node(fields.get(1).ok_or("tag record without content")?.node);
+                VariantContent::Value(
+                    self.with(object.get(content).unwrap_or(&Value::Null), schema),
+                )
+            }
+            _ => VariantContent::Fields(JsonFields {
+                deserializer: self,
+                object,
+                fields: &fields[1..],
+            }),
+        };
+        Ok((variant, content))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for JsonDeserializer<'_, 'de> {
+    type Error = SerializationError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (this, _) = self.branch()?;
+        let (value, schema) = (this.value, this.schema);
+        match schema {
+            Node::Null if value.is_null() => visitor.visit_unit(),
+            Node::Boolean => visitor.visit_bool(value.as_bool().ok_or_else(|| this.mismatch())?),
+            Node::Int | Node::Long => visitor.visit_i64(this.integer()?),
+            Node::Float | Node::Double => {
+                visitor.visit_f64(value.as_f64().ok_or_else(|| this.mismatch())?)
+            }
+            Node::Bytes | Node::Fixed { .. } | Node::Duration { .. } => {
+                visitor.visit_byte_buf(decode_bytes(value)?)
+            }
+            Node::String | Node::Uuid | Node::Enum { .. } => {
+                visitor.visit_borrowed_str(this.string()?)
+            }
+            Node::Date => visitor.visit_string(logical::format_date(this.integer()?)),
+            Node::TimeMillis => {
+                visitor.visit_string(logical::format_time(this.integer()? * 1_000_000))
+            }
+            Node::TimeMicros => visitor.visit_string(logical::format_time(this.integer()? * 1_000)),
+            Node::TimestampMillis => visitor.visit_string(logical::format_timestamp(
+                this.integer()? as i128 * 1_000_000,
+            )),
+            Node::TimestampMicros => {
+                visitor.visit_string(logical::format_timestamp(this.integer()? as i128 * 1_000))
+            }
+            Node::Decimal { scale, .. } => {
+                let decimal = logical::format_decimal(&decode_bytes(value)?, *scale)
+                    .ok_or("decimal doesn't fit in 128 bits")?;
+                visitor.visit_string(decimal)
+            }
+            Node::Array(items) => {
+                let values = value.as_array().ok_or_else(|| this.mismatch())?;
+                visitor.visit_seq(JsonSeq {
+                    deserializer: this,
+                    schema: this.node(*items),
+                    values: values.iter(),
+                })
+            }
+            Node::Map(values) => {
+                let object = value.as_object().ok_or_else(|| this.mismatch())?;
+                visitor.visit_map(JsonMap {
+                    deserializer: this,
+                    schema: this.node(*values),
+                    entries: object.iter(),
+                    value: None,
+                })
+            }
+            Node::Record { fields, .. } => {
+                let object = value.as_object().ok_or_else(|| this.mismatch())?;
+                visitor.visit_map(JsonFields {
+                    deserializer: this,
+                    object,
+                    fields,
+                })
+            }
+            _ => Err(this.mismatch()),
+        }
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (this, _) = self.branch()?;
+        match this.value {
+            Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => visitor.visit_i64(i),
+                (_, Some(u)) => visitor.visit_u64(u),
+                _ => this.deserialize_any(visitor),
+            },
+            _ => this.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (this, _) = self.branch()?;
+        match this.schema {
+            Node::Decimal { .. } => visitor.visit_byte_buf(decode_bytes(this.value)?),
+            _ => this.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.schema {
+            Node::Null | Node::Nullable { .. } if self.value.is_null() => visitor.visit_none(),
+            Node::Union { variant_index, .. } if self.value.is_null() => {
+                if !variant_index.contains_key(&SerializationSchemaKind::Null) {
+                    return Err(SerializationError::SchemaMismatch {
+                        expected: Box::new(self.schema.to_schema(self.nodes)),
+                        found: SerializationSchemaKind::Null,
+                        path: Default::default(),
+                    });
+                }
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (variant, content) = self.variant(variants)?;
+        visitor.visit_enum(JsonEnum { variant, content })
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool char str string unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_any(visitor)
+    }
+}
+
+struct JsonSeq<'a, 'de> {
+    deserializer: JsonDeserializer<'a, 'de>,
+    schema: &'a Node,
+    values: std::slice::Iter<'de, Value>,
+}
+
+impl<'de> de::SeqAccess<'de> for JsonSeq<'_, 'de> {
+    type Error = SerializationError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.values
+            .next()
+            .map(|value| seed.deserialize(self.deserializer.with(value, self.schema)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct JsonMap<'a, 'de> {
+    deserializer: JsonDeserializer<'a, 'de>,
+    schema: &'a Node,
+    entries: serde_json::map::Iter<'de>,
+    value: Option<&'de Value>,
+}
+
+impl<'de> de::MapAccess<'de> for JsonMap<'_, 'de> {
+    type Error = SerializationError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        seed.deserialize(BorrowedStrDeserializer::<SerializationError>::new(key))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = self.value.take().ok_or("map value without key")?;
+        seed.deserialize(self.deserializer.with(value, self.schema))
+    }
+}
+
+/// Record fields present in the JSON object; missing fields are left to the visitor.
+struct JsonFields<'a, 'de> {
+    deserializer: JsonDeserializer<'a, 'de>,
+    object: &'de Map<String, Value>,
+    fields: &'a [Field],
+}
+
+impl<'de> de::MapAccess<'de> for JsonFields<'_, 'de> {
+    type Error = SerializationError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        while let Some(field) = self.fields.first() {
+            if self.object.contains_key(&field.name) {
+                return seed
+                    .deserialize(field.name.as_str().into_deserializer())
+                    .map(Some);
+            }
+            self.fields = &self.fields[1..];
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let (field, fields) = self
+            .fields
+            .split_first()
+            .ok_or("record value without field")?;
+        self.fields = fields;
+        let schema = self.deserializer.node(field.node);
+        seed.deserialize(self.deserializer.with(&self.object[&field.name], schema))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for JsonFields<'_, 'de> {
+    type Error = SerializationError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+struct JsonEnum<'a, 'de> {
+    variant: &'de str,
+    content: VariantContent<'a, 'de>,
+}
+
+impl<'a, 'de> de::EnumAccess<'de> for JsonEnum<'a, 'de> {
+    type Error = SerializationError;
+    type Variant = VariantContent<'a, 'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(BorrowedStrDeserializer::<SerializationError>::new(
+            self.variant,
+        ))?;
+        Ok((variant, self.content))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantContent<'_, 'de> {
+    type Error = SerializationError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self {
+            VariantContent::Unit => Err("expected unit variant".into()),
+            VariantContent::Value(deserializer) => seed.deserialize(deserializer),
+            VariantContent::Fields(fields) => seed.deserialize(fields),
+        }
+    }
+
+    fn tuple_variant<V>(self, _: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            VariantContent::Value(deserializer) => {
+                de::Deserializer::deserialize_seq(deserializer, visitor)
+            }
+            _ => Err("expected tuple variant".into()),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            VariantContent::Unit => Err("expected struct variant".into()),
+            VariantContent::Value(deserializer) => {
+                de::Deserializer::deserialize_any(deserializer, visitor)
+            }
+            VariantContent::Fields(fields) => visitor.visit_map(fields),
+        }
+    }
+}
@@ -1,60 +1,508 @@
-use std::{collections::HashMap, io};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+    io,
+    sync::{Arc, OnceLock, RwLock},
+};
 
+#[cfg(feature = "apache-avro")]
 use apache_avro::{AvroResult, Error, Schema};
-use error::SerializationError;
-use ser::SerializerRef;
-use serde::Serialize;
+use json::{JsonDeserializer, JsonSerializer};
+use read::Reader;
+use ser::{SerializerRef, WithSegment};
+use serde::{de::DeserializeOwned, ser::Error as _, Serialize};
+use transcode::{BinaryDatum, JsonEncoder};
+use utils::{BufferedWriter, ByteCounter};
+use value::Decoder;
 
-use crate::schema::{SerializationSchemaKind, SerializationSchemaWithRefs};
+use crate::{node::CompiledSchema, schema::SerializationSchemas};
+#[cfg(feature = "derive")]
+pub use avro_poc_derive::AvroSchema;
 
+pub use crate::{
+    avro_schema::AvroSchema,
+    batch::Batch,
+    config::{SerializerConfig, VariantRepresentation},
+    error::{PathSegment, SchemaError, SerializationError},
+    fingerprint::Fingerprint,
+    schema::{
+        FieldOrder, Name, Namespace, RecordField, SerializationSchema, SerializationSchemaKind,
+        Symbols,
+    },
+    value::Value,
+};
+
+#[cfg(feature = "apache-avro")]
+mod apache;
+mod avro_schema;
+mod batch;
+pub mod codegen;
+pub mod compatibility;
+mod config;
 mod error;
+mod fingerprint;
+pub mod idl;
+mod json;
+mod logical;
+mod node;
+mod parser;
+mod read;
 mod schema;
 mod ser;
+mod transcode;
 mod utils;
+mod value;
+
+/// Compiled schema, shared between clones, and usable from any thread.
+///
+/// Schemas are compiled once per process: serializers built from the same schemas and
+/// configuration share the schema compiled for the first one.
+#[derive(Clone)]
+pub struct Serializer {
+    schema: Arc<CompiledSchema>,
+    config: Arc<SerializerConfig>,
+    fingerprint: Fingerprint,
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Serializer>();
+};
 
-pub struct Serializer(SerializationSchemaWithRefs);
+/// Number of serializers kept in the cache, beyond which the oldest ones are evicted.
+const CACHE_CAPACITY: usize = 256;
+
+static CACHE: OnceLock<RwLock<Cache>> = OnceLock::new();
+
+/// Serializers compiled so far, by fingerprint. As the canonical form leaves out logical types
+/// and defaults, which change the serialization, serializers sharing it are told apart by their
+/// schemas and configuration.
+#[derive(Default)]
+struct Cache {
+    serializers: HashMap<Fingerprint, Vec<Serializer>>,
+    /// Fingerprints of the cached serializers, from the oldest.
+    order: VecDeque<Fingerprint>,
+}
+
+impl Cache {
+    /// Serializer compiled from the schemas with the configuration.
+    fn get(
+        &self,
+        fingerprint: &Fingerprint,
+        root: &SerializationSchema,
+        dependencies: &[SerializationSchema],
+        config: &SerializerConfig,
+    ) -> Option<Serializer> {
+        self.serializers
+            .get(fingerprint)?
+            .iter()
+            .find(|s| {
+                *s.config == *config
+                    && s.schema.root == *root
+                    && s.schema.dependencies == dependencies
+            })
+            .cloned()
+    }
+
+    /// Evicting a serializer doesn't affect its clones, which keep the compiled schema alive.
+    fn insert(&mut self, serializer: Serializer) {
+        if self.order.len() == CACHE_CAPACITY {
+            let oldest = self.order.pop_front().unwrap();
+            if let Entry::Occupied(mut entry) = self.serializers.entry(oldest) {
+                entry.get_mut().remove(0);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+        self.order.push_back(serializer.fingerprint);
+        self.serializers
+            .entry(serializer.fingerprint)
+            .or_default()
+            .push(serializer);
+    }
+}
 
 impl Serializer {
+    #[cfg(feature = "apache-avro")]
     pub fn new(schema: &Schema) -> AvroResult<Self> {
-        let mut ref_indexes = HashMap::new();
-        let optimized_schema = schema::to_serialization_schema(schema, &mut ref_indexes, &None);
-        let internal = SerializationSchemaWithRefs::try_new(optimized_schema, move |s| {
-            let mut refs = vec![None; ref_indexes.len()];
-            schema::set_refs(s, &ref_indexes, &mut refs);
-            refs.into_iter()
-                .enumerate()
-                .map(|(index, r#ref)| {
-                    r#ref.ok_or_else(|| {
-                        Error::SchemaResolutionError(
-                            ref_indexes
-                                .iter()
-                                .find_map(|(name, i)| (index == *i).then_some(name))
-                                .unwrap()
-                                .clone(),
-                        )
-                    })
-                })
-                .collect()
-        })?;
-        Ok(Self(internal))
+        Self::with_config(schema, SerializerConfig::default())
+    }
+
+    #[cfg(feature = "apache-avro")]
+    pub fn with_config(schema: &Schema, config: SerializerConfig) -> AvroResult<Self> {
+        Self::with_dependencies(schema, &[], config)
+    }
+
+    /// Named types referenced by `schema` may be defined in any of the `dependencies`.
+    #[cfg(feature = "apache-avro")]
+    pub fn with_dependencies(
+        schema: &Schema,
+        dependencies: &[Schema],
+        config: SerializerConfig,
+    ) -> AvroResult<Self> {
+        let optimized_schemas = SerializationSchemas {
+            root: schema::to_serialization_schema(schema, &None)?,
+            dependencies: dependencies
+                .iter()
+                .map(|s| schema::to_serialization_schema(s, &None))
+                .collect::<AvroResult<_>>()?,
+        };
+        Self::compile(optimized_schemas, config).map_err(|name| {
+            Error::SchemaResolutionError(apache_avro::schema::Name {
+                name: name.name,
+                namespace: name.namespace,
+            })
+        })
+    }
+
+    /// Parse the JSON schema without going through [`apache_avro`].
+    pub fn parse_str(input: &str) -> Result<Self, SchemaError> {
+        Self::parse_with_dependencies(input, &[], SerializerConfig::default())
     }
 
+    /// Named types referenced by `input` may be defined in any of the `dependencies`.
+    pub fn parse_with_dependencies(
+        input: &str,
+        dependencies: &[&str],
+        config: SerializerConfig,
+    ) -> Result<Self, SchemaError> {
+        let dependencies = dependencies
+            .iter()
+            .map(|s| serde_json::from_str(s))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::parse_json(&serde_json::from_str(input)?, &dependencies, config)
+    }
+
+    /// Build the schema of a type implementing [`AvroSchema`].
+    pub fn for_type<T: AvroSchema + ?Sized>() -> Result<Self, SchemaError> {
+        Self::parse_json(
+            &T::json_schema(&mut HashSet::new()),
+            &[],
+            SerializerConfig::default(),
+        )
+    }
+
+    fn parse_json(
+        json: &serde_json::Value,
+        dependencies: &[serde_json::Value],
+        config: SerializerConfig,
+    ) -> Result<Self, SchemaError> {
+        let optimized_schemas = SerializationSchemas {
+            root: parser::parse_schema(json, &None)?,
+            dependencies: dependencies
+                .iter()
+                .map(|s| parser::parse_schema(s, &None))
+                .collect::<Result<_, _>>()?,
+        };
+        Self::compile(optimized_schemas, config).map_err(SchemaError::UnresolvedRef)
+    }
+
+    /// Compile the schemas, unless a serializer was already compiled from the same schemas and
+    /// configuration.
+    fn compile(schemas: SerializationSchemas, config: SerializerConfig) -> Result<Self, Name> {
+        let fingerprint = Fingerprint::of_canonical_form(&schemas.canonical_form());
+        let cache = CACHE.get_or_init(Default::default);
+        let (root, dependencies) = (&schemas.root, &schemas.dependencies);
+        let cached = cache
+            .read()
+            .unwrap()
+            .get(&fingerprint, root, dependencies, &config);
+        if let Some(serializer) = cached {
+            return Ok(serializer);
+        }
+        let serializer = Self {
+            fingerprint,
+            schema: Arc::new(CompiledSchema::new(schemas)?),
+            config: Arc::new(config),
+        };
+        let mut cache = cache.write().unwrap();
+        let (schema, config) = (&serializer.schema, &serializer.config);
+        // Another thread may have compiled the schema in the meantime.
+        if let Some(cached) = cache.get(&fingerprint, &schema.root, &schema.dependencies, config) {
+            return Ok(cached);
+        }
+        cache.insert(serializer.clone());
+        Ok(serializer)
+    }
+
+    pub fn schema(&self) -> &SerializationSchema {
+        &self.schema.root
+    }
+
+    /// [Parsing Canonical Form](https://avro.apache.org/docs/current/specification/#parsing-canonical-form-for-schemas)
+    /// of the schema, with the types defined in its dependencies.
+    pub fn canonical_form(&self) -> String {
+        self.schema.canonical_form()
+    }
+
+    /// Fingerprint of the [canonical form](Self::canonical_form) of the schema, which can be
+    /// used to identify it, e.g. as a cache key.
+    pub fn fingerprint(&self) -> &Fingerprint {
+        &self.fingerprint
+    }
+
+    /// Write the binary encoding of the value; writes are buffered, so the writer doesn't need
+    /// to be. To append to a `Vec`, [`Serializer::serialize_into`] spares the buffer copy.
     pub fn write(
         &self,
         value: &impl Serialize,
         writer: impl io::Write,
     ) -> Result<(), SerializationError> {
-        SerializerRef {
+        let writer = BufferedWriter::new(writer);
+        let mut serializer = SerializerRef::new(writer, &self.schema, &self.config);
+        serializer.serialize(value)?;
+        serializer.writer.flush_buffer()?;
+        Ok(())
+    }
+
+    /// Like [`Serializer::write`], for in-memory writers, which don't benefit from buffering.
+    fn write_unbuffered(
+        &self,
+        value: &impl Serialize,
+        writer: impl io::Write,
+    ) -> Result<(), SerializationError> {
+        SerializerRef::new(writer, &self.schema, &self.config).serialize(value)
+    }
+
+    pub fn serialize(&self, value: &impl Serialize) -> Result<Vec<u8>, SerializationError> {
+        let mut vec = Vec::new();
+        self.write_unbuffered(value, &mut vec)?;
+        Ok(vec)
+    }
+
+    /// Append the binary encoding of the value to the buffer, which is left unchanged in case of
+    /// error.
+    pub fn serialize_into(
+        &self,
+        value: &impl Serialize,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), SerializationError> {
+        let len = buf.len();
+        self.write_unbuffered(value, &mut *buf)
+            .inspect_err(|_| buf.truncate(len))
+    }
+
+    /// Write the binary encoding of the value at the start of the buffer, returning its length.
+    pub fn serialize_into_slice(
+        &self,
+        value: &impl Serialize,
+        buf: &mut [u8],
+    ) -> Result<usize, SerializationError> {
+        let len = buf.len();
+        let mut remaining = buf;
+        match self.write_unbuffered(value, &mut remaining) {
+            Ok(()) => Ok(len - remaining.len()),
+            Err(SerializationError::Io(error)) if error.kind() == io::ErrorKind::WriteZero => {
+                Err(SerializationError::BufferTooSmall {
+                    len,
+                    needed: self.serialized_size(value)?,
+                })
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Append the binary encoding of the value to the buffer, e.g. a [`bytes::BytesMut`], which is
+    /// left unchanged in case of error.
+    ///
+    /// As a [`bytes::BufMut`] cannot be truncated, the value is encoded in a vector then copied
+    /// into the buffer: it is walked once, where sizing it first would walk it twice. Prefer
+    /// [`Serializer::serialize_into`] when the buffer is a `Vec<u8>`.
+    #[cfg(feature = "bytes")]
+    pub fn serialize_into_buf(
+        &self,
+        value: &impl Serialize,
+        buf: &mut impl bytes::BufMut,
+    ) -> Result<(), SerializationError> {
+        let bytes = self.serialize(value)?;
+        if buf.remaining_mut() < bytes.len() {
+            return Err(SerializationError::BufferTooSmall {
+                len: buf.remaining_mut(),
+                needed: bytes.len(),
+            });
+        }
+        buf.put_slice(&bytes);
+        Ok(())
+    }
+
+    /// Serialize the values one after the other in a single buffer.
+    pub fn serialize_batch(
+        &self,
+        values: impl IntoIterator<Item = impl Serialize>,
+    ) -> Result<Batch, SerializationError> {
+        let mut batch = Batch::new();
+        self.serialize_batch_into(values, &mut batch)?;
+        Ok(batch)
+    }
+
+    /// Append the values to the batch, which is left unchanged in case of error; the error path
+    /// starts with the index of the faulty value.
+    pub fn serialize_batch_into(
+        &self,
+        values: impl IntoIterator<Item = impl Serialize>,
+        batch: &mut Batch,
+    ) -> Result<(), SerializationError> {
+        let (buffer_len, len) = (batch.buffer.len(), batch.len());
+        let root = self.schema.root_node();
+        let mut serializer = SerializerRef::new(&mut batch.buffer, &self.schema, &self.config);
+        let result = values
+            .into_iter()
+            .enumerate()
+            .try_for_each(|(index, value)| {
+                // The schema is changed while serializing nested values.
+                serializer.schema = root;
+                serializer
+                    .serialize(value)
+                    .with_segment(|| PathSegment::Index(index))?;
+                batch.ends.push(serializer.writer.len());
+                Ok(())
+            });
+        if result.is_err() {
+            batch.buffer.truncate(buffer_len);
+            batch.ends.truncate(len);
+        }
+        result
+    }
+
+    /// Exact length of the binary encoding of the value, computed by running the serialization
+    /// without storing the bytes.
+    pub fn serialized_size(&self, value: &impl Serialize) -> Result<usize, SerializationError> {
+        let mut counter = ByteCounter::default();
+        self.write_unbuffered(value, &mut counter)?;
+        Ok(counter.0)
+    }
+
+    /// Check that the value matches the schema, running the serialization without writing
+    /// anything.
+    pub fn validate(&self, value: &impl Serialize) -> Result<(), SerializationError> {
+        self.write_unbuffered(value, io::sink())
+    }
+
+    /// Like [`Serializer::validate`], but reports every error instead of stopping at the first.
+    pub fn validate_all(&self, value: &impl Serialize) -> Result<(), Vec<SerializationError>> {
+        let mut serializer = SerializerRef::new(io::sink(), &self.schema, &self.config);
+        serializer.errors = Some(Vec::new());
+        serializer
+            .serialize(value)
+            .map_err(SerializationError::into_errors)
+    }
+
+    /// Write the value in the [Avro JSON encoding](https://avro.apache.org/docs/current/specification/#json-encoding).
+    pub fn write_json(
+        &self,
+        value: &impl Serialize,
+        writer: impl io::Write,
+    ) -> Result<(), SerializationError> {
+        let json = value.serialize(JsonSerializer {
+            schema: self.schema.root_node(),
+            nodes: &self.schema.nodes,
+            config: &self.config,
+        })?;
+        serde_json::to_writer(writer, &json)?;
+        Ok(())
+    }
+
+    pub fn serialize_json(&self, value: &impl Serialize) -> Result<String, SerializationError> {
+        let mut vec = Vec::new();
+        self.write_json(value, &mut vec)?;
+        Ok(String::from_utf8(vec).expect("JSON is UTF-8"))
+    }
+
+    /// Read a value from the Avro JSON encoding; logical types are read as strings, e.g. RFC 3339
+    /// timestamps, unless an integer or bytes are requested.
+    pub fn deserialize_json<T: DeserializeOwned>(
+        &self,
+        input: &str,
+    ) -> Result<T, SerializationError> {
+        let value: serde_json::Value = serde_json::from_str(input)?;
+        T::deserialize(JsonDeserializer {
+            value: &value,
+            schema: self.schema.root_node(),
+            nodes: &self.schema.nodes,
+            config: &self.config,
+        })
+    }
+
+    /// Encode an [`apache_avro::types::Value`] directly, honouring the branch index of unions and
+    /// matching record fields by name.
+    #[cfg(feature = "apache-avro")]
+    pub fn write_apache_value(
+        &self,
+        value: &apache_avro::types::Value,
+        writer: impl io::Write,
+    ) -> Result<(), SerializationError> {
+        apache::ApacheEncoder {
             writer,
-            schema: self.0.borrow_owner(),
-            refs: self.0.borrow_dependent(),
+            nodes: &self.schema.nodes,
         }
-        .serialize(value)
+        .encode(value, self.schema.root_node())
     }
 
-    pub fn serialize(&self, value: &impl Serialize) -> Result<Vec<u8>, SerializationError> {
+    #[cfg(feature = "apache-avro")]
+    pub fn serialize_apache_value(
+        &self,
+        value: &apache_avro::types::Value,
+    ) -> Result<Vec<u8>, SerializationError> {
         let mut vec = Vec::new();
-        self.write(value, &mut vec)?;
+        self.write_apache_value(value, &mut vec)?;
         Ok(vec)
     }
+
+    /// Decode a binary datum into a dynamic [`Value`], borrowing strings and bytes from the input.
+    pub fn decode<'a>(&'a self, input: &'a [u8]) -> Result<Value<'a>, SerializationError> {
+        let decoder = Decoder {
+            reader: Reader::new(input),
+            nodes: &self.schema.nodes,
+        };
+        let value = decoder.decode(self.schema.root_node())?;
+        match decoder.reader.remaining().len() {
+            0 => Ok(value),
+            len => Err(format!("{len} trailing bytes").into()),
+        }
+    }
+
+    /// Transcode a binary datum to the Avro JSON encoding without going through Rust types, e.g.
+    /// with `&mut serde_json::Serializer::new(writer)`.
+    pub fn binary_to_json<S: serde::Serializer>(
+        &self,
+        input: &[u8],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let reader = Reader::new(input);
+        let ok = BinaryDatum {
+            schema: self.schema.root_node(),
+            nodes: &self.schema.nodes,
+            reader: &reader,
+        }
+        .serialize(serializer)?;
+        match reader.remaining().len() {
+            0 => Ok(ok),
+            len => Err(S::Error::custom(format!("{len} trailing bytes"))),
+        }
+    }
+
+    /// Transcode a value in the Avro JSON encoding to a binary datum without going through Rust
+    /// types.
+    ///
+    /// The input is read entirely before anything is written, as record fields may appear in any
+    /// order in JSON but must be encoded in schema order.
+    pub fn json_to_binary(
+        &self,
+        input: impl io::Read,
+        writer: impl io::Write,
+    ) -> Result<(), SerializationError> {
+        let value: serde_json::Value = serde_json::from_reader(input)?;
+        JsonEncoder {
+            writer,
+            nodes: &self.schema.nodes,
+        }
+        .encode(&value, self.schema.root_node(), false)
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+
+    pub use crate::avro_schema::RECURSIVE_UNION;
 }
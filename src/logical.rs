@@ -0,0 +1,221 @@
+/// Days since the Unix epoch of a `YYYY-MM-DD` date.
+pub(crate) fn parse_date(s: &str) -> Option<i32> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let mut parts = s.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parse_digits(parts.next()?, 2)?;
+    let day: u32 = parse_digits(parts.next()?, 2)?;
+    let year = if negative { -year } else { year };
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    days_from_civil(year, month, day).try_into().ok()
+}
+
+/// Nanoseconds since midnight of a `HH:MM:SS[.fraction]` time.
+pub(crate) fn parse_time(s: &str) -> Option<i64> {
+    let mut parts = s.splitn(3, ':');
+    let hours: i64 = parse_digits(parts.next()?, 2)?;
+    let minutes: i64 = parse_digits(parts.next()?, 2)?;
+    let seconds = parts.next()?;
+    let (seconds, fraction) = match seconds.split_once('.') {
+        Some((seconds, fraction)) => (seconds, Some(fraction)),
+        None => (seconds, None),
+    };
+    let seconds: i64 = parse_digits(seconds, 2)?;
+    // A leap second is allowed.
+    if hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+    let nanos = match fraction {
+        Some(fraction) if fraction.is_empty() || fraction.len() > 9 => return None,
+        Some(fraction) => {
+            parse_digits::<i64>(fraction, fraction.len())? * 10i64.pow(9 - fraction.len() as u32)
+        }
+        None => 0,
+    };
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1_000_000_000 + nanos)
+}
+
+/// Nanoseconds since the Unix epoch of an RFC 3339 timestamp; timestamps without offset are
+/// considered to be UTC.
+pub(crate) fn parse_timestamp(s: &str) -> Option<i128> {
+    let separator = s.find(['T', 't', ' '])?;
+    let (date, time) = (&s[..separator], &s[separator + 1..]);
+    let (time, offset) = if let Some(time) = time.strip_suffix(['Z', 'z']) {
+        (time, 0)
+    } else if let Some(index) = time.rfind(['+', '-']) {
+        let (time, offset) = time.split_at(index);
+        let (sign, offset) = offset.split_at(1);
+        let (hours, minutes) = match offset.split_once(':') {
+            Some((hours, minutes)) => (hours, minutes),
+            None if offset.len() == 4 => offset.split_at(2),
+            None => (offset, "00"),
+        };
+        let offset =
+            parse_digits::<i128>(hours, 2)? * 3600 + parse_digits::<i128>(minutes, 2)? * 60;
+        (time, if sign == "-" { -offset } else { offset })
+    } else {
+        (time, 0)
+    };
+    let days = parse_date(date)? as i128;
+    let nanos = parse_time(time)? as i128;
+    Some((days * 86_400 - offset) * 1_000_000_000 + nanos)
+}
+
+/// Big-endian two's complement bytes of the unscaled value of a decimal string, which must fit
+/// the precision and scale.
+pub(crate) fn decimal_bytes(s: &str, precision: usize, scale: usize) -> Option<Vec<u8>> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (integer, fraction) = s.split_once('.').unwrap_or((s, ""));
+    let fraction = fraction.trim_end_matches('0');
+    if (integer.is_empty() && fraction.is_empty())
+        || fraction.len() > scale
+        || !integer
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let integer = integer.trim_start_matches('0');
+    if integer.len() + scale > precision {
+        return None;
+    }
+    let mut unscaled: i128 = 0;
+    for digit in integer
+        .bytes()
+        .chain(fraction.bytes())
+        .chain(std::iter::repeat_n(b'0', scale - fraction.len()))
+    {
+        unscaled = unscaled
+            .checked_mul(10)?
+            .checked_add((digit - b'0') as i128)?;
+    }
+    if negative {
+        unscaled = -unscaled;
+    }
+    let bytes = unscaled.to_be_bytes();
+    // Keep a single sign byte.
+    let start = (0..bytes.len() - 1)
+        .find(|&i| {
+            !(bytes[i] == 0 && bytes[i + 1] & 0x80 == 0
+                || bytes[i] == 0xff && bytes[i + 1] & 0x80 != 0)
+        })
+        .unwrap_or(bytes.len() - 1);
+    Some(bytes[start..].to_vec())
+}
+
+/// Sign extend big-endian two's complement bytes to `size`.
+pub(crate) fn sign_extend(bytes: &[u8], size: usize) -> Option<Vec<u8>> {
+    let padding = size.checked_sub(bytes.len())?;
+    let sign = match bytes.first() {
+        Some(b) if b & 0x80 != 0 => 0xff,
+        _ => 0,
+    };
+    let mut extended = vec![sign; padding];
+    extended.extend_from_slice(bytes);
+    Some(extended)
+}
+
+fn parse_digits<T: std::str::FromStr>(s: &str, len: usize) -> Option<T> {
+    if s.len() != len || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// See <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// `YYYY-MM-DD` date of days since the Unix epoch.
+pub(crate) fn format_date(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// `HH:MM:SS[.fraction]` time of nanoseconds since midnight.
+pub(crate) fn format_time(nanos: i64) -> String {
+    let seconds = nanos / 1_000_000_000;
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    match nanos % 1_000_000_000 {
+        0 => time,
+        nanos if nanos % 1_000_000 == 0 => format!("{time}.{:03}", nanos / 1_000_000),
+        nanos if nanos % 1_000 == 0 => format!("{time}.{:06}", nanos / 1_000),
+        nanos => format!("{time}.{nanos:09}"),
+    }
+}
+
+/// RFC 3339 UTC timestamp of nanoseconds since the Unix epoch.
+pub(crate) fn format_timestamp(nanos: i128) -> String {
+    let days = nanos.div_euclid(86_400_000_000_000);
+    let nanos = nanos.rem_euclid(86_400_000_000_000);
+    format!(
+        "{}T{}Z",
+        format_date(days as i64),
+        format_time(nanos as i64)
+    )
+}
+
+/// Decimal string of big-endian two's complement bytes of an unscaled value.
+pub(crate) fn format_decimal(bytes: &[u8], scale: usize) -> Option<String> {
+    if bytes.len() > 16 {
+        return None;
+    }
+    let sign = match bytes.first() {
+        Some(b) if b & 0x80 != 0 => 0xff,
+        _ => 0,
+    };
+    let mut extended = [sign; 16];
+    extended[16 - bytes.len()..].copy_from_slice(bytes);
+    let unscaled = i128::from_be_bytes(extended);
+    let digits = format!("{:0>width$}", unscaled.unsigned_abs(), width = scale + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - scale);
+    let sign = if unscaled < 0 { "-" } else { "" };
+    Some(match fraction {
+        "" => format!("{sign}{integer}"),
+        _ => format!("{sign}{integer}.{fraction}"),
+    })
+}
+
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,554 @@
+//! Compiled form of the schemas: a flat arena of nodes, in which the children of a node and the
+//! references to named types are both ids of other nodes, so that the compiled schema owns all
+//! its nodes and recursive types are plain cycles of ids.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, ptr,
+    sync::OnceLock,
+};
+
+use serde_json::Value;
+
+use crate::schema::{
+    self, FieldOrder, Name, RecordField, SerializationSchema, SerializationSchemaKind,
+    SerializationSchemas, Symbols,
+};
+
+/// Index of a node in the arena.
+pub(crate) type NodeId = u32;
+
+/// Id of the node of the root schema.
+pub(crate) const ROOT: NodeId = 0;
+
+/// Schemas ready to be serialized: the parsed schemas, and the nodes compiled from them.
+#[derive(Debug)]
+pub(crate) struct CompiledSchema {
+    pub(crate) root: SerializationSchema,
+    pub(crate) dependencies: Vec<SerializationSchema>,
+    /// Nodes of the root schema, starting with [`ROOT`], then of the dependencies.
+    pub(crate) nodes: Box<[Node]>,
+    /// Field plans of each record, kept out of the nodes as they are written once used.
+    pub(crate) plans: Box<[FieldPlans]>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Node {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Array(NodeId),
+    Map(NodeId),
+    Union {
+        branches: Box<[NodeId]>,
+        variant_index: VariantIndex,
+    },
+    /// Two-branch union with a null branch, e.g. `["null", "string"]`, the other branch being at
+    /// `1 - null_index`; branches are selected without looking up their kind.
+    Nullable {
+        branches: Box<[NodeId]>,
+        null_index: usize,
+    },
+    Record {
+        named: Box<Named>,
+        fields: Box<[Field]>,
+        /// Index of the field plans of the record in [`CompiledSchema::plans`].
+        plans: u32,
+    },
+    Enum {
+        named: Box<Named>,
+        symbols: Box<Symbols>,
+        default: Option<String>,
+    },
+    Fixed {
+        named: Box<Named>,
+        size: usize,
+    },
+    Decimal {
+        precision: usize,
+        scale: usize,
+        inner: NodeId,
+    },
+    Uuid,
+    Date,
+    TimeMillis,
+    TimeMicros,
+    TimestampMillis,
+    TimestampMicros,
+    Duration {
+        inner: NodeId,
+    },
+}
+
+/// Name and metadata of a named type, boxed as they are not needed to serialize values.
+#[derive(Debug)]
+pub(crate) struct Named {
+    pub(crate) name: Name,
+    pub(crate) aliases: Vec<Name>,
+    pub(crate) doc: Option<String>,
+    pub(crate) attributes: BTreeMap<String, Value>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Field {
+    pub(crate) name: String,
+    pub(crate) node: NodeId,
+    pub(crate) default: Option<Value>,
+    pub(crate) aliases: Vec<String>,
+    pub(crate) doc: Option<String>,
+    pub(crate) order: FieldOrder,
+    pub(crate) attributes: BTreeMap<String, Value>,
+}
+
+/// Branch of each kind found in a single branch of a union. Unions have a few branches, whose
+/// kinds are scanned faster than they would be looked up in a map.
+#[derive(Debug)]
+pub(crate) struct VariantIndex(Box<[(SerializationSchemaKind, usize)]>);
+
+impl VariantIndex {
+    fn new(kinds: &[SerializationSchemaKind]) -> Self {
+        let unique = |kind| kinds.iter().filter(|k| **k == kind).count() == 1;
+        Self(
+            kinds
+                .iter()
+                .enumerate()
+                .filter(|(_, kind)| unique(**kind))
+                .map(|(index, kind)| (*kind, index))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn get(&self, kind: &SerializationSchemaKind) -> Option<&usize> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == kind)
+            .map(|(_, index)| index)
+    }
+
+    pub(crate) fn contains_key(&self, kind: &SerializationSchemaKind) -> bool {
+        self.get(kind).is_some()
+    }
+}
+
+/// Number of Rust types whose field plan is cached for a record; other types are serialized by
+/// looking up their fields by name.
+const MAX_FIELD_PLANS: usize = 4;
+
+/// Field plans of the Rust types serialized into a record, keyed by type name.
+///
+/// Plans are only set once, so they are read without locking.
+#[derive(Default)]
+pub(crate) struct FieldPlans([OnceLock<FieldPlan>; MAX_FIELD_PLANS]);
+
+/// Serde keys of a Rust type fields, in serialization order, with the index of their record
+/// field.
+pub(crate) struct FieldPlan {
+    type_name: &'static str,
+    pub(crate) keys: Box<[(&'static str, usize)]>,
+    /// Position of the key of each field, in schema order, if the keys are not in schema order.
+    pub(crate) order: Option<Box<[usize]>>,
+}
+
+impl CompiledSchema {
+    /// Compile the schemas, failing with the name of the first reference to an undefined type.
+    pub(crate) fn new(schemas: SerializationSchemas) -> Result<Self, Name> {
+        let mut compiler = Compiler {
+            definitions: schema::definitions(
+                [&schemas.root].into_iter().chain(&schemas.dependencies),
+            ),
+            ids: HashMap::new(),
+            nodes: Vec::new(),
+            records: 0,
+        };
+        for schema in [&schemas.root].into_iter().chain(&schemas.dependencies) {
+            compiler.compile(schema)?;
+        }
+        let (nodes, records) = (compiler.nodes.into(), compiler.records);
+        Ok(Self {
+            root: schemas.root,
+            dependencies: schemas.dependencies,
+            nodes,
+            plans: (0..records).map(|_| FieldPlans::default()).collect(),
+        })
+    }
+
+    /// Node of the root schema.
+    pub(crate) fn root_node(&self) -> &Node {
+        &self.nodes[ROOT as usize]
+    }
+}
+
+struct Compiler<'a> {
+    definitions: HashMap<&'a Name, &'a SerializationSchema>,
+    /// Ids of the compiled schemas, by address, so that named types are compiled once.
+    ids: HashMap<*const SerializationSchema, NodeId>,
+    nodes: Vec<Node>,
+    /// Number of records compiled so far.
+    records: u32,
+}
+
+impl<'a> Compiler<'a> {
+    fn resolve(&self, schema: &'a SerializationSchema) -> Result<&'a SerializationSchema, Name> {
+        match schema {
+            SerializationSchema::Ref { name } => self
+                .definitions
+                .get(name)
+                .copied()
+                .ok_or_else(|| name.clone()),
+            _ => Ok(schema),
+        }
+    }
+
+    fn compile(&mut self, schema: &'a SerializationSchema) -> Result<NodeId, Name> {
+        let schema = self.resolve(schema)?;
+        if let Some(&id) = self.ids.get(&ptr::from_ref(schema)) {
+            return Ok(id);
+        }
+        // The id is taken before compiling the children, which may reference the node.
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node::Null);
+        self.ids.insert(schema, id);
+        let node = match schema {
+            SerializationSchema::Null => Node::Null,
+            SerializationSchema::Boolean => Node::Boolean,
+            SerializationSchema::Int => Node::Int,
+            SerializationSchema::Long => Node::Long,
+            SerializationSchema::Float => Node::Float,
+            SerializationSchema::Double => Node::Double,
+            SerializationSchema::Bytes => Node::Bytes,
+            SerializationSchema::String => Node::String,
+            SerializationSchema::Array(items) => Node::Array(self.compile(items)?),
+            SerializationSchema::Map(values) => Node::Map(self.compile(values)?),
+            SerializationSchema::Union { schemas } => self.union(schemas)?,
+            SerializationSchema::Record {
+                name,
+                aliases,
+                doc,
+                fields,
+                attributes,
+            } => Node::Record {
+                named: Named::new(name, aliases, doc, attributes),
+                fields: fields
+                    .iter()
+                    .map(|field| {
+                        Ok(Field {
+                            name: field.name.clone(),
+                            node: self.compile(&field.schema)?,
+                            default: field.default.clone(),
+                            aliases: field.aliases.clone(),
+                            doc: field.doc.clone(),
+                            order: field.order,
+                            attributes: field.attributes.clone(),
+                        })
+                    })
+                    .collect::<Result<_, Name>>()?,
+                plans: self.record_plans(),
+            },
+            SerializationSchema::Enum {
+                name,
+                aliases,
+                doc,
+                symbols,
+                default,
+                attributes,
+            } => Node::Enum {
+                named: Named::new(name, aliases, doc, attributes),
+                symbols: Box::new(symbols.clone()),
+                default: default.clone(),
+            },
+            SerializationSchema::Fixed {
+                name,
+                aliases,
+                doc,
+                size,
+                attributes,
+            } => Node::Fixed {
+                named: Named::new(name, aliases, doc, attributes),
+                size: *size,
+            },
+            SerializationSchema::Decimal {
+                precision,
+                scale,
+                inner,
+            } => Node::Decimal {
+                precision: *precision,
+                scale: *scale,
+                inner: self.compile(inner)?,
+            },
+            SerializationSchema::Uuid => Node::Uuid,
+            SerializationSchema::Date => Node::Date,
+            SerializationSchema::TimeMillis => Node::TimeMillis,
+            SerializationSchema::TimeMicros => Node::TimeMicros,
+            SerializationSchema::TimestampMillis => Node::TimestampMillis,
+            SerializationSchema::TimestampMicros => Node::TimestampMicros,
+            SerializationSchema::Duration { inner } => Node::Duration {
+                inner: self.compile(inner)?,
+            },
+            SerializationSchema::Ref { .. } => unreachable!(),
+        };
+        self.nodes[id as usize] = node;
+        Ok(id)
+    }
+
+    /// Index of the field plans of a new record.
+    fn record_plans(&mut self) -> u32 {
+        self.records += 1;
+        self.records - 1
+    }
+
+    /// Branches are indexed by their resolved kind, as the node of a type being compiled is not
+    /// built yet.
+    fn union(&mut self, schemas: &'a [SerializationSchema]) -> Result<Node, Name> {
+        let kinds = schemas
+            .iter()
+            .map(|schema| self.resolve(schema).map(SerializationSchemaKind::from))
+            .collect::<Result<Vec<_>, _>>()?;
+        let branches = schemas
+            .iter()
+            .map(|schema| self.compile(schema))
+            .collect::<Result<_, _>>()?;
+        let null_index = match kinds[..] {
+            [SerializationSchemaKind::Null, _] => 0,
+            [_, SerializationSchemaKind::Null] => 1,
+            _ => {
+                return Ok(Node::Union {
+                    branches,
+                    variant_index: VariantIndex::new(&kinds),
+                })
+            }
+        };
+        Ok(Node::Nullable {
+            branches,
+            null_index,
+        })
+    }
+}
+
+impl FieldPlans {
+    /// Keys and names are compared by address, as serde keys are static; types sharing a name
+    /// are told apart by their keys.
+    pub(crate) fn get(&self, type_name: &'static str) -> Option<&FieldPlan> {
+        self.0
+            .iter()
+            .map_while(OnceLock::get)
+            .find(|plan| ptr::eq(plan.type_name, type_name))
+    }
+
+    /// Cache the plan of a type, unless all plans are already taken; the keys must be those of
+    /// every field of the record.
+    pub(crate) fn insert(&self, type_name: &'static str, keys: &[(&'static str, usize)]) {
+        let in_order = keys
+            .iter()
+            .enumerate()
+            .all(|(position, (_, index))| position == *index);
+        let order = (!in_order).then(|| {
+            let mut order = vec![0; keys.len()];
+            for (position, (_, index)) in keys.iter().enumerate() {
+                order[*index] = position;
+            }
+            order.into()
+        });
+        let mut plan = Some(FieldPlan {
+            type_name,
+            keys: keys.into(),
+            order,
+        });
+        for slot in &self.0 {
+            match slot.get() {
+                Some(plan) if ptr::eq(plan.type_name, type_name) => return,
+                Some(_) => {}
+                None => match slot.set(plan.take().unwrap()) {
+                    Ok(()) => return,
+                    Err(not_set) => plan = Some(not_set),
+                },
+            }
+        }
+    }
+}
+
+impl fmt::Debug for FieldPlans {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map_while(OnceLock::get).map(|p| p.type_name))
+            .finish()
+    }
+}
+
+impl Named {
+    fn new(
+        name: &Name,
+        aliases: &[Name],
+        doc: &Option<String>,
+        attributes: &BTreeMap<String, Value>,
+    ) -> Box<Self> {
+        Box::new(Self {
+            name: name.clone(),
+            aliases: aliases.to_vec(),
+            doc: doc.clone(),
+            attributes: attributes.clone(),
+        })
+    }
+}
+
+impl Node {
+    pub(crate) fn named(&self) -> Option<&Named> {
+        match self {
+            Node::Record { named, .. } | Node::Enum { named, .. } | Node::Fixed { named, .. } => {
+                Some(named)
+            }
+            _ => None,
+        }
+    }
+
+    /// Branches of a union, nullable or not.
+    pub(crate) fn branches(&self) -> Option<&[NodeId]> {
+        match self {
+            Node::Union { branches, .. } | Node::Nullable { branches, .. } => Some(branches),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(&self) -> Option<&Name> {
+        self.named().map(|named| &named.name)
+    }
+
+    pub(crate) fn aliases(&self) -> &[Name] {
+        self.named().map_or(&[], |named| &named.aliases)
+    }
+
+    /// Whether the node is a named type matching `s`, by its name or one of its aliases.
+    pub(crate) fn is_named(&self, s: &str) -> bool {
+        self.name()
+            .into_iter()
+            .chain(self.aliases())
+            .any(|name| schema::name_matches(name, s))
+    }
+
+    /// Schema of the node, e.g. to report errors; named types are written in full at their first
+    /// occurrence and referenced afterwards.
+    pub(crate) fn to_schema(&self, nodes: &[Node]) -> SerializationSchema {
+        let mut written = HashSet::new();
+        if let Some(id) = nodes.iter().position(|node| ptr::eq(node, self)) {
+            written.insert(id as NodeId);
+        }
+        self.rebuild(nodes, &mut written)
+    }
+
+    fn rebuild(&self, nodes: &[Node], written: &mut HashSet<NodeId>) -> SerializationSchema {
+        let mut child = |id: NodeId| {
+            let node = &nodes[id as usize];
+            match node.name() {
+                Some(name) if !written.insert(id) => {
+                    SerializationSchema::Ref { name: name.clone() }
+                }
+                _ => node.rebuild(nodes, written),
+            }
+        };
+        match self {
+            Node::Null => SerializationSchema::Null,
+            Node::Boolean => SerializationSchema::Boolean,
+            Node::Int => SerializationSchema::Int,
+            Node::Long => SerializationSchema::Long,
+            Node::Float => SerializationSchema::Float,
+            Node::Double => SerializationSchema::Double,
+            Node::Bytes => SerializationSchema::Bytes,
+            Node::String => SerializationSchema::String,
+            Node::Array(items) => SerializationSchema::Array(Box::new(child(*items))),
+            Node::Map(values) => SerializationSchema::Map(Box::new(child(*values))),
+            Node::Union { branches, .. } | Node::Nullable { branches, .. } => {
+                SerializationSchema::Union {
+                    schemas: branches.iter().map(|id| child(*id)).collect(),
+                }
+            }
+            Node::Record { named, fields, .. } => SerializationSchema::Record {
+                name: named.name.clone(),
+                aliases: named.aliases.clone(),
+                doc: named.doc.clone(),
+                fields: fields
+                    .iter()
+                    .map(|field| RecordField {
+                        name: field.name.clone(),
+                        schema: child(field.node),
+                        default: field.default.clone(),
+                        doc: field.doc.clone(),
+                        aliases: field.aliases.clone(),
+                        order: field.order,
+                        attributes: field.attributes.clone(),
+                    })
+                    .collect(),
+                attributes: named.attributes.clone(),
+            },
+            Node::Enum {
+                named,
+                symbols,
+                default,
+            } => SerializationSchema::Enum {
+                name: named.name.clone(),
+                aliases: named.aliases.clone(),
+                doc: named.doc.clone(),
+                symbols: Symbols::clone(symbols),
+                default: default.clone(),
+                attributes: named.attributes.clone(),
+            },
+            Node::Fixed { named, size } => SerializationSchema::Fixed {
+                name: named.name.clone(),
+                aliases: named.aliases.clone(),
+                doc: named.doc.clone(),
+                size: *size,
+                attributes: named.attributes.clone(),
+            },
+            Node::Decimal {
+                precision,
+                scale,
+                inner,
+            } => SerializationSchema::Decimal {
+                precision: *precision,
+                scale: *scale,
+                inner: Box::new(child(*inner)),
+            },
+            Node::Uuid => SerializationSchema::Uuid,
+            Node::Date => SerializationSchema::Date,
+            Node::TimeMillis => SerializationSchema::TimeMillis,
+            Node::TimeMicros => SerializationSchema::TimeMicros,
+            Node::TimestampMillis => SerializationSchema::TimestampMillis,
+            Node::TimestampMicros => SerializationSchema::TimestampMicros,
+            Node::Duration { inner } => SerializationSchema::Duration {
+                inner: Box::new(child(*inner)),
+            },
+        }
+    }
+}
+
+impl From<&Node> for SerializationSchemaKind {
+    fn from(node: &Node) -> Self {
+        match node {
+            Node::Null => SerializationSchemaKind::Null,
+            Node::Boolean => SerializationSchemaKind::Boolean,
+            Node::Int => SerializationSchemaKind::Int,
+            Node::Long => SerializationSchemaKind::Long,
+            Node::Float => SerializationSchemaKind::Float,
+            Node::Double => SerializationSchemaKind::Double,
+            Node::Bytes => SerializationSchemaKind::Bytes,
+            Node::String => SerializationSchemaKind::String,
+            Node::Array(_) => SerializationSchemaKind::Array,
+            Node::Map(_) => SerializationSchemaKind::Map,
+            Node::Union { .. } | Node::Nullable { .. } => SerializationSchemaKind::Union,
+            Node::Record { .. } => SerializationSchemaKind::Record,
+            Node::Enum { .. } => SerializationSchemaKind::Enum,
+            Node::Fixed { .. } => SerializationSchemaKind::Fixed,
+            Node::Decimal { .. } => SerializationSchemaKind::Decimal,
+            Node::Uuid => SerializationSchemaKind::Uuid,
+            Node::Date => SerializationSchemaKind::Date,
+            Node::TimeMillis => SerializationSchemaKind::TimeMillis,
+            Node::TimeMicros => SerializationSchemaKind::TimeMicros,
+            Node::TimestampMillis => SerializationSchemaKind::TimestampMillis,
+            Node::TimestampMicros => SerializationSchemaKind::TimestampMicros,
+            Node::Duration { .. } => SerializationSchemaKind::Duration,
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use crate::{
+    avro_schema::RECURSIVE_UNION,
+    error::SchemaError,
+    schema::{
+        FieldOrder, Name, Namespace, RecordField, SerializationSchema, SerializationSchemaKind,
+        Symbols,
+    },
+};
+
+const RECORD_KEYS: &[&str] = &["type", "name", "namespace", "doc", "aliases", "fields"];
+const FIELD_KEYS: &[&str] = &["name", "type", "default", "doc", "aliases", "order"];
+const ENUM_KEYS: &[&str] = &[
+    "type",
+    "name",
+    "namespace",
+    "doc",
+    "aliases",
+    "symbols",
+    "default",
+];
+const FIXED_KEYS: &[&str] = &["type", "name", "namespace", "doc", "aliases", "size"];
+
+/// Parse a JSON schema, named types references being resolved later, as for
+/// [`to_serialization_schema`](crate::schema::to_serialization_schema).
+pub fn parse_schema(
+    json: &Value,
+    enclosing_namespace: &Namespace,
+) -> Result<SerializationSchema, SchemaError> {
+    match json {
+        Value::String(name) => match primitive(name) {
+            Some(schema) => Ok(schema),
+            None => {
+                let name = Name::new(name).fully_qualified_name(enclosing_namespace);
+                check_name(&name)?;
+                Ok(SerializationSchema::Ref { name })
+            }
+        },
+        Value::Array(schemas) => {
+            let schemas = schemas
+                .iter()
+                .map(|s| parse_schema(s, enclosing_namespace))
+                .collect::<Result<Vec<_>, _>>()?;
+            if schemas
+                .iter()
+                .any(|s| matches!(s, SerializationSchema::Union { .. }))
+            {
+                return Err("union cannot contain another union".into());
+            }
+            check_union_branches(&schemas)?;
+            Ok(SerializationSchema::Union { schemas })
+        }
+        Value::Object(object) => parse_object(object, enclosing_namespace),
+        _ => Err(format!("expected schema, found {json}").into()),
+    }
+}
+
+/// Only named types may share their type with other branches, provided they have distinct names.
+fn check_union_branches(schemas: &[SerializationSchema]) -> Result<(), SchemaError> {
+    let mut names = Vec::new();
+    let mut kinds = Vec::new();
+    for schema in schemas {
+        let name = match schema {
+            SerializationSchema::Decimal { inner, .. }
+            | SerializationSchema::Duration { inner } => inner.name(),
+            _ => schema.name(),
+        };
+        match name {
+            Some(name) if names.contains(&name) => {
+                return Err(format!("duplicate union branch {name}").into());
+            }
+            Some(name) => names.push(name),
+            None => {
+                let kind = SerializationSchemaKind::from(schema);
+                if kinds.contains(&kind) {
+                    return Err(format!("duplicate union branch {kind}").into());
+                }
+                kinds.push(kind);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn primitive(name: &str) -> Option<SerializationSchema> {
+    Some(match name {
+        "null" => SerializationSchema::Null,
+        "boolean" => SerializationSchema::Boolean,
+        "int" => SerializationSchema::Int,
+        "long" => SerializationSchema::Long,
+        "float" => SerializationSchema::Float,
+        "double" => SerializationSchema::Double,
+        "bytes" => SerializationSchema::Bytes,
+        "string" => SerializationSchema::String,
+        _ => return None,
+    })
+}
+
+fn parse_object(
+    object: &Map<String, Value>,
+    enclosing_namespace: &Namespace,
+) -> Result<SerializationSchema, SchemaError> {
+    let r#type = get(object, "type")?;
+    let schema = match r#type.as_str() {
+        Some("record" | "error") => parse_record(object, enclosing_namespace)?,
+        Some("enum") => parse_enum(object, enclosing_namespace)?,
+        Some("fixed") => parse_fixed(object, enclosing_namespace)?,
+        Some("array") => SerializationSchema::Array(Box::new(
+            parse_schema(get(object, "items")?, enclosing_namespace).with_path("items")?,
+        )),
+        Some("map") => SerializationSchema::Map(Box::new(
+            parse_schema(get(object, "values")?, enclosing_namespace).with_path("values")?,
+        )),
+        Some(RECURSIVE_UNION) => {
+            let name = get(object, "name")?;
+            return Err(format!("recursive enum {name} cannot be represented as a union").into());
+        }
+        _ => parse_schema(r#type, enclosing_namespace)?,
+    };
+    Ok(match object.get("logicalType").and_then(Value::as_str) {
+        Some(logical_type) => with_logical_type(logical_type, object, schema),
+        None => schema,
+    })
+}
+
+/// Invalid or unknown logical types are ignored, as required by the specification.
+fn with_logical_type(
+    logical_type: &str,
+    object: &Map<String, Value>,
+    schema: SerializationSchema,
+) -> SerializationSchema {
+    match (logical_type, &schema) {
+        ("decimal", SerializationSchema::Bytes | SerializationSchema::Fixed { .. }) => {
+            let precision = object.get("precision").and_then(Value::as_u64);
+            let scale = object.get("scale").map_or(Some(0), Value::as_u64);
+            match (precision, scale) {
+                (Some(precision), Some(scale)) if precision > 0 && scale <= precision => {
+                    SerializationSchema::Decimal {
+                        precision: precision as usize,
+                        scale: scale as usize,
+                        inner: Box::new(schema),
+                    }
+                }
+                _ => schema,
+            }
+        }
+        ("uuid", SerializationSchema::String) => SerializationSchema::Uuid,
+        ("date", SerializationSchema::Int) => SerializationSchema::Date,
+        ("time-millis", SerializationSchema::Int) => SerializationSchema::TimeMillis,
+        ("time-micros", SerializationSchema::Long) => SerializationSchema::TimeMicros,
+        ("timestamp-millis", SerializationSchema::Long) => SerializationSchema::TimestampMillis,
+        ("timestamp-micros", SerializationSchema::Long) => SerializationSchema::TimestampMicros,
+        ("duration", SerializationSchema::Fixed { size: 12, .. }) => {
+            SerializationSchema::Duration {
+                inner: Box::new(schema),
+            }
+        }
+        _ => schema,
+    }
+}
+
+fn parse_record(
+    object: &Map<String, Value>,
+    enclosing_namespace: &Namespace,
+) -> Result<SerializationSchema, SchemaError> {
+    let name = parse_name(object, enclosing_namespace)?;
+    let type_name = name.name.clone();
+    let fields = get(object, "fields")?
+        .as_array()
+        .ok_or("record fields must be an array")
+        .map_err(SchemaError::from)
+        .with_path(&type_name)?
+        .iter()
+        .map(|field| parse_field(field, &name.namespace))
+        .collect::<Result<Vec<_>, _>>()
+        .with_path(&type_name)?;
+    let mut names = fields.iter().map(|f| &f.name).collect::<Vec<_>>();
+    names.sort();
+    if let Some(duplicate) = names.windows(2).find(|w| w[0] == w[1]) {
+        return Err(format!("duplicate field {}", duplicate[0]).into()).with_path(&type_name);
+    }
+    Ok(SerializationSchema::Record {
+        aliases: parse_aliases(object, &name.namespace)?,
+        doc: parse_doc(object)?,
+        fields,
+        attributes: attributes(object, RECORD_KEYS),
+        name,
+    })
+}
+
+fn parse_field(json: &Value, namespace: &Namespace) -> Result<RecordField, SchemaError> {
+    let Value::Object(object) = json else {
+        return Err(format!("expected record field, found {json}").into());
+    };
+    let name = get_str(object, "name")?;
+    check_simple_name(name)?;
+    let schema = parse_schema(get(object, "type")?, namespace).with_path(name)?;
+    let order = match object.get("order").map(|o| o.as_str()) {
+        None | Some(Some("ascending")) => FieldOrder::Ascending,
+        Some(Some("descending")) => FieldOrder::Descending,
+        Some(Some("ignore")) => FieldOrder::Ignore,
+        Some(_) => return Err("invalid field order".into()).with_path(name),
+    };
+    let aliases = match object.get("aliases") {
+        None => Vec::new(),
+        Some(Value::Array(aliases)) => aliases
+            .iter()
+            .map(|alias| match alias {
+                Value::String(alias) => Ok(alias.clone()),
+                _ => Err(format!("expected field alias, found {alias}").into()),
+            })
+            .collect::<Result<_, SchemaError>>()
+            .with_path(name)?,
+        Some(_) => return Err("field aliases must be an array".into()).with_path(name),
+    };
+    Ok(RecordField {
+        name: name.into(),
+        schema,
+        default: object.get("default").cloned(),
+        doc: parse_doc(object).with_path(name)?,
+        aliases,
+        order,
+        attributes: attributes(object, FIELD_KEYS),
+    })
+}
+
+fn parse_enum(
+    object: &Map<String, Value>,
+    enclosing_namespace: &Namespace,
+) -> Result<SerializationSchema, SchemaError> {
+    let name = parse_name(object, enclosing_namespace)?;
+    let type_name = name.name.clone();
+    let Some(symbol_list) = get(object, "symbols")?.as_array() else {
+        return Err("enum symbols must be an array".into()).with_path(&type_name);
+    };
+    let mut symbols = Vec::new();
+    for symbol in symbol_list {
+        let Some(symbol) = symbol.as_str() else {
+            return Err(format!("expected enum symbol, found {symbol}").into())
+                .with_path(&type_name);
+        };
+        check_simple_name(symbol).with_path(&type_name)?;
+        symbols.push(symbol.to_string());
+    }
+    let symbols = Symbols::new(symbols).with_path(&type_name)?;
+    let default = match object.get("default") {
+        None => None,
+        Some(Value::String(default)) if symbols.contains(default) => Some(default.clone()),
+        Some(default) => {
+            return Err(format!("invalid enum default {default}").into()).with_path(&type_name)
+        }
+    };
+    Ok(SerializationSchema::Enum {
+        aliases: parse_aliases(object, &name.namespace)?,
+        doc: parse_doc(object)?,
+        symbols,
+        default,
+        attributes: attributes(object, ENUM_KEYS),
+        name,
+    })
+}
+
+fn parse_fixed(
+    object: &Map<String, Value>,
+    enclosing_namespace: &Namespace,
+) -> Result<SerializationSchema, SchemaError> {
+    let name = parse_name(object, enclosing_namespace)?;
+    // Sizes are Java ints in the reference implementation.
+    let size = get(object, "size")?.as_u64();
+    let Some(size) = size.filter(|size| (1..=i32::MAX as u64).contains(size)) else {
+        return Err("fixed size must be a positive integer".into()).with_path(&name.name);
+    };
+    Ok(SerializationSchema::Fixed {
+        aliases: parse_aliases(object, &name.namespace)?,
+        doc: parse_doc(object)?,
+        size: size as usize,
+        attributes: attributes(object, FIXED_KEYS),
+        name,
+    })
+}
+
+/// A dotted name carries its own namespace, otherwise it is taken from the `namespace`
+/// attribute, or from the enclosing one.
+fn parse_name(
+    object: &Map<String, Value>,
+    enclosing_namespace: &Namespace,
+) -> Result<Name, SchemaError> {
+    let mut name = Name::new(get_str(object, "name")?);
+    if name.namespace.is_none() {
+        name.namespace = match object.get("namespace") {
+            None | Some(Value::Null) => enclosing_namespace.clone(),
+            Some(Value::String(namespace)) => Some(namespace.clone()),
+            Some(namespace) => return Err(format!("invalid namespace {namespace}").into()),
+        };
+    }
+    let name = name.fully_qualified_name(&None);
+    check_name(&name)?;
+    Ok(name)
+}
+
+fn parse_aliases(
+    object: &Map<String, Value>,
+    namespace: &Namespace,
+) -> Result<Vec<Name>, SchemaError> {
+    let Some(aliases) = object.get("aliases") else {
+        return Ok(Vec::new());
+    };
+    let Some(aliases) = aliases.as_array() else {
+        return Err("aliases must be an array".into());
+    };
+    aliases
+        .iter()
+        .map(|alias| {
+            let Some(alias) = alias.as_str() else {
+                return Err(format!("expected alias, found {alias}").into());
+            };
+            let alias = Name::new(alias).fully_qualified_name(namespace);
+            check_name(&alias)?;
+            Ok(alias)
+        })
+        .collect()
+}
+
+fn parse_doc(object: &Map<String, Value>) -> Result<Option<String>, SchemaError> {
+    match object.get("doc") {
+        None => Ok(None),
+        Some(Value::String(doc)) => Ok(Some(doc.clone())),
+        Some(doc) => Err(format!("expected doc, found {doc}").into()),
+    }
+}
+
+fn attributes(object: &Map<String, Value>, reserved: &[&str]) -> BTreeMap<String, Value> {
+    object
+        .iter()
+        .filter(|(key, _)| !reserved.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+fn get<'a>(object: &'a Map<String, Value>, key: &str) -> Result<&'a Value, SchemaError> {
+    object
+        .get(key)
+        .ok_or_else(|| format!("missing \"{key}\" attribute").into())
+}
+
+fn get_str<'a>(object: &'a Map<String, Value>, key: &str) -> Result<&'a str, SchemaError> {
+    get(object, key)?
+        .as_str()
+        .ok_or_else(|| format!("\"{key}\" attribute must be a string").into())
+}
+
+fn check_name(name: &Name) -> Result<(), SchemaError> {
+    check_simple_name(&name.name)?;
+    name.namespace
+        .iter()
+        .flat_map(|ns| ns.split('.'))
+        .try_for_each(check_simple_name)
+}
+
+fn check_simple_name(name: &str) -> Result<(), SchemaError> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("invalid name {name:?}").into());
+    }
+    Ok(())
+}
+
+trait WithPath {
+    fn with_path(self, segment: &str) -> Self;
+}
+
+impl<T> WithPath for Result<T, SchemaError> {
+    fn with_path(mut self, segment: &str) -> Self {
+        if let Err(SchemaError::Invalid { ref mut path, .. }) = self {
+            path.push_front(segment.into());
+        }
+        self
+    }
+}
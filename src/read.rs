@@ -0,0 +1,94 @@
+use std::cell::Cell;
+
+use integer_encoding::VarInt;
+
+use crate::error::SerializationError;
+
+/// Reads Avro binary encoded primitives, consuming the input through a shared reference so that
+/// it can be used from [`serde::Serialize`] implementations.
+pub(crate) struct Reader<'de> {
+    input: Cell<&'de [u8]>,
+}
+
+impl<'de> Reader<'de> {
+    pub(crate) fn new(input: &'de [u8]) -> Self {
+        Self {
+            input: Cell::new(input),
+        }
+    }
+
+    pub(crate) fn remaining(&self) -> &'de [u8] {
+        self.input.get()
+    }
+
+    fn varint<T: VarInt>(&self) -> Result<T, SerializationError> {
+        let input = self.input.get();
+        let (n, len) = T::decode_var(input).ok_or("invalid varint")?;
+        self.input.set(&input[len..]);
+        Ok(n)
+    }
+
+    pub(crate) fn read_fixed(&self, size: usize) -> Result<&'de [u8], SerializationError> {
+        let input = self.input.get();
+        if input.len() < size {
+            return Err(format!("expected {size} bytes, found {}", input.len()).into());
+        }
+        let (bytes, input) = input.split_at(size);
+        self.input.set(input);
+        Ok(bytes)
+    }
+
+    pub(crate) fn read_bool(&self) -> Result<bool, SerializationError> {
+        match self.read_fixed(1)? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            [b] => Err(format!("invalid boolean {b}").into()),
+            _ => unreachable!(),
+        }
+    }
+
+    pub(crate) fn read_int(&self) -> Result<i32, SerializationError> {
+        self.varint()
+    }
+
+    pub(crate) fn read_long(&self) -> Result<i64, SerializationError> {
+        self.varint()
+    }
+
+    pub(crate) fn read_float(&self) -> Result<f32, SerializationError> {
+        Ok(f32::from_le_bytes(self.read_fixed(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn read_double(&self) -> Result<f64, SerializationError> {
+        Ok(f64::from_le_bytes(self.read_fixed(8)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn read_bytes(&self) -> Result<&'de [u8], SerializationError> {
+        let len = self.read_long()?;
+        let len = usize::try_from(len).map_err(|_| format!("invalid length {len}"))?;
+        self.read_fixed(len)
+    }
+
+    pub(crate) fn read_str(&self) -> Result<&'de str, SerializationError> {
+        std::str::from_utf8(self.read_bytes()?).map_err(|error| error.to_string().into())
+    }
+
+    /// Item count of the next array or map block, zero at the end; the byte size of blocks
+    /// having a negative count is skipped.
+    pub(crate) fn read_block_len(&self) -> Result<usize, SerializationError> {
+        let len = self.read_long()?;
+        if len < 0 {
+            self.read_long()?;
+        }
+        Ok(len.unsigned_abs() as usize)
+    }
+
+    /// Branch index of a union.
+    pub(crate) fn read_index(&self, len: usize) -> Result<usize, SerializationError> {
+        let index = self.read_long()?;
+        usize::try_from(index)
+            .ok()
+            .filter(|&index| index < len)
+            .ok_or_else(|| format!("invalid index {index}").into())
+    }
+}
@@ -1,17 +1,92 @@
 use std::{
-    collections::{BTreeMap, HashMap},
-    slice,
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, slice,
 };
 
-use apache_avro::{
-    schema::{Name, Namespace},
-    Schema,
-};
+#[cfg(feature = "apache-avro")]
+use apache_avro::{schema::Aliases, AvroResult, Error, Schema};
+use serde_json::Value;
+
+use crate::SchemaError;
+
+pub type Namespace = Option<String>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Name {
+    pub name: String,
+    pub namespace: Namespace,
+}
+
+impl Name {
+    /// Split a possibly fully qualified name, e.g. `a.b.C`, into namespace and simple name.
+    pub fn new(name: &str) -> Self {
+        match name.rsplit_once('.') {
+            Some((namespace, name)) => Self {
+                name: name.into(),
+                namespace: (!namespace.is_empty()).then(|| namespace.into()),
+            },
+            None => Self {
+                name: name.into(),
+                namespace: None,
+            },
+        }
+    }
+
+    pub fn fully_qualified_name(&self, enclosing_namespace: &Namespace) -> Self {
+        Self {
+            name: self.name.clone(),
+            namespace: self
+                .namespace
+                .clone()
+                .or_else(|| enclosing_namespace.clone())
+                .filter(|ns| !ns.is_empty()),
+        }
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{namespace}.{}", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[cfg(feature = "apache-avro")]
+impl From<&apache_avro::schema::Name> for Name {
+    fn from(name: &apache_avro::schema::Name) -> Self {
+        Self {
+            name: name.name.clone(),
+            namespace: name.namespace.clone(),
+        }
+    }
+}
 
-#[derive(Debug, Clone, strum::EnumDiscriminants)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FieldOrder {
+    #[default]
+    Ascending,
+    Descending,
+    Ignore,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordField {
+    pub name: String,
+    pub schema: SerializationSchema,
+    pub default: Option<Value>,
+    pub doc: Option<String>,
+    pub aliases: Vec<String>,
+    pub order: FieldOrder,
+    pub attributes: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, strum::EnumDiscriminants)]
 #[strum_discriminants(
     name(SerializationSchemaKind),
-    derive(Hash, Ord, PartialOrd, strum::Display)
+    derive(Hash, Ord, PartialOrd, strum::Display, strum::IntoStaticStr)
 )]
 pub enum SerializationSchema {
     Null,
@@ -26,19 +101,28 @@ pub enum SerializationSchema {
     Map(Box<SerializationSchema>),
     Union {
         schemas: Vec<SerializationSchema>,
-        variant_index: BTreeMap<SerializationSchemaKind, usize>,
     },
     Record {
         name: Name,
-        fields: Vec<(String, SerializationSchema)>,
+        aliases: Vec<Name>,
+        doc: Option<String>,
+        fields: Vec<RecordField>,
+        attributes: BTreeMap<String, Value>,
     },
     Enum {
         name: Name,
-        symbols: BTreeMap<String, usize>,
+        aliases: Vec<Name>,
+        doc: Option<String>,
+        symbols: Symbols,
+        default: Option<String>,
+        attributes: BTreeMap<String, Value>,
     },
     Fixed {
         name: Name,
+        aliases: Vec<Name>,
+        doc: Option<String>,
         size: usize,
+        attributes: BTreeMap<String, Value>,
     },
     Decimal {
         precision: usize,
@@ -51,30 +135,137 @@ pub enum SerializationSchema {
     TimeMicros,
     TimestampMillis,
     TimestampMicros,
-    Duration,
+    /// Logical type of a fixed of 12 bytes, kept to serialize its name and let it be referenced.
+    Duration {
+        inner: Box<SerializationSchema>,
+    },
     Ref {
         name: Name,
-        index: usize,
     },
 }
 
-type Refs<'a> = Vec<&'a SerializationSchema>;
+/// Enum symbols in schema order, looked up by name through a perfect hash table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbols {
+    symbols: Vec<String>,
+    seed: u64,
+    /// Displacement of each bucket, selecting the slots of the symbols hashed into it.
+    displacements: Vec<u64>,
+    /// Index of the symbol in each slot, or `u32::MAX` for empty slots.
+    slots: Vec<u32>,
+}
+
+impl Symbols {
+    /// Build the hash table with the "hash and displace" algorithm: symbols are hashed into
+    /// buckets, then the largest buckets are the first to get a displacement placing all their
+    /// symbols into free slots.
+    pub fn new(symbols: Vec<String>) -> Result<Self, SchemaError> {
+        if let Some(symbol) = duplicate(&symbols) {
+            return Err(format!("duplicate enum symbol {symbol}").into());
+        }
+        let mask = (2 * symbols.len()).next_power_of_two() - 1;
+        let mut seed = 0;
+        'seed: loop {
+            let hashes = symbols.iter().map(|s| hash(seed, s)).collect::<Vec<_>>();
+            let mut buckets = vec![Vec::new(); mask + 1];
+            for (index, hash) in hashes.iter().enumerate() {
+                buckets[*hash as usize & mask].push(index);
+            }
+            let mut order = (0..buckets.len()).collect::<Vec<_>>();
+            order.sort_by_key(|bucket| Reverse(buckets[*bucket].len()));
+            let mut displacements = vec![0; mask + 1];
+            let mut slots = vec![u32::MAX; mask + 1];
+            let mut bucket_slots = Vec::new();
+            'bucket: for bucket in order.into_iter().take_while(|b| !buckets[*b].is_empty()) {
+                'displacement: for displacement in 0..=mask as u64 {
+                    bucket_slots.clear();
+                    for index in &buckets[bucket] {
+                        let slot = slot(hashes[*index], displacement, mask);
+                        if slots[slot] != u32::MAX || bucket_slots.contains(&slot) {
+                            continue 'displacement;
+                        }
+                        bucket_slots.push(slot);
+                    }
+                    for (slot, index) in bucket_slots.iter().zip(&buckets[bucket]) {
+                        slots[*slot] = *index as u32;
+                    }
+                    displacements[bucket] = displacement;
+                    continue 'bucket;
+                }
+                seed += 1;
+                continue 'seed;
+            }
+            return Ok(Self {
+                symbols,
+                seed,
+                displacements,
+                slots,
+            });
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
 
-self_cell::self_cell!(
-    pub(crate) struct SerializationSchemaWithRefs {
-        owner: SerializationSchema,
-        #[covariant]
-        dependent: Refs,
+    /// Symbols in schema order.
+    pub fn iter(&self) -> slice::Iter<'_, String> {
+        self.symbols.iter()
     }
-    impl {Debug}
-);
 
+    /// Symbol at the given index.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.symbols.get(index).map(String::as_str)
+    }
+
+    /// Index of the given symbol.
+    pub fn position(&self, symbol: &str) -> Option<usize> {
+        let mask = self.slots.len() - 1;
+        let hash = hash(self.seed, symbol);
+        let slot = slot(hash, self.displacements[hash as usize & mask], mask);
+        let index = self.slots[slot] as usize;
+        (self.symbols.get(index)? == symbol).then_some(index)
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.position(symbol).is_some()
+    }
+}
+
+/// Seeded FNV-1a, with a final mix so that both halves of the hash are well distributed.
+fn hash(seed: u64, s: &str) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    for byte in s.bytes() {
+        hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^ (hash >> 33)
+}
+
+/// The step is odd, so that the displacements of a bucket go through every slot.
+fn slot(hash: u64, displacement: u64, mask: usize) -> usize {
+    let (start, step) = (hash >> 32, (hash >> 16) | 1);
+    start.wrapping_add(displacement.wrapping_mul(step)) as usize & mask
+}
+
+/// The schema to serialize, and the schemas defining the named types it may reference.
+#[derive(Debug)]
+pub(crate) struct SerializationSchemas {
+    pub(crate) root: SerializationSchema,
+    pub(crate) dependencies: Vec<SerializationSchema>,
+}
+
+#[cfg(feature = "apache-avro")]
 pub fn to_serialization_schema(
     schema: &Schema,
-    ref_indexes: &mut HashMap<Name, usize>,
     enclosing_namespace: &Namespace,
-) -> SerializationSchema {
-    match schema {
+) -> AvroResult<SerializationSchema> {
+    Ok(match schema {
         Schema::Null => SerializationSchema::Null,
         Schema::Boolean => SerializationSchema::Boolean,
         Schema::Int => SerializationSchema::Int,
@@ -85,66 +276,96 @@ pub fn to_serialization_schema(
         Schema::String => SerializationSchema::String,
         Schema::Array(schema) => SerializationSchema::Array(Box::new(to_serialization_schema(
             schema,
-            ref_indexes,
             enclosing_namespace,
-        ))),
+        )?)),
         Schema::Map(schema) => SerializationSchema::Map(Box::new(to_serialization_schema(
             schema,
-            ref_indexes,
             enclosing_namespace,
-        ))),
-        Schema::Union(schema) => {
-            let schemas: Vec<_> = schema
+        )?)),
+        Schema::Union(schema) => SerializationSchema::Union {
+            schemas: schema
                 .variants()
                 .iter()
-                .map(|s| to_serialization_schema(s, ref_indexes, enclosing_namespace))
-                .collect();
-            let variant_index = schemas
-                .iter()
-                .enumerate()
-                .map(|(i, s)| (SerializationSchemaKind::from(s), i))
-                .collect();
-            SerializationSchema::Union {
-                schemas,
-                variant_index,
-            }
-        }
-        Schema::Record { name, fields, .. } => {
-            let fully_qualified_name = name.fully_qualified_name(enclosing_namespace);
+                .map(|s| to_serialization_schema(s, enclosing_namespace))
+                .collect::<AvroResult<_>>()?,
+        },
+        Schema::Record {
+            name,
+            aliases,
+            doc,
+            fields,
+            attributes,
+            ..
+        } => {
+            let fully_qualified_name = Name::from(name).fully_qualified_name(enclosing_namespace);
             let optimized_fields = fields
                 .iter()
                 .map(|field| {
-                    (
-                        field.name.clone(),
-                        to_serialization_schema(
+                    Ok(RecordField {
+                        name: field.name.clone(),
+                        schema: to_serialization_schema(
                             &field.schema,
-                            ref_indexes,
                             &fully_qualified_name.namespace,
-                        ),
-                    )
+                        )?,
+                        default: field.default.clone(),
+                        doc: field.doc.clone(),
+                        aliases: field.aliases.clone().unwrap_or_default(),
+                        order: match field.order {
+                            apache_avro::schema::RecordFieldOrder::Ascending => {
+                                FieldOrder::Ascending
+                            }
+                            apache_avro::schema::RecordFieldOrder::Descending => {
+                                FieldOrder::Descending
+                            }
+                            apache_avro::schema::RecordFieldOrder::Ignore => FieldOrder::Ignore,
+                        },
+                        attributes: field.custom_attributes.clone(),
+                    })
                 })
-                .collect();
+                .collect::<AvroResult<_>>()?;
             SerializationSchema::Record {
+                aliases: to_aliases(aliases, &fully_qualified_name.namespace),
                 name: fully_qualified_name,
+                doc: doc.clone(),
                 fields: optimized_fields,
+                attributes: attributes.clone(),
             }
         }
-        Schema::Enum { name, symbols, .. } => {
-            let fully_qualified_name = name.fully_qualified_name(enclosing_namespace);
+        Schema::Enum {
+            name,
+            aliases,
+            doc,
+            symbols,
+            default,
+            attributes,
+        } => {
+            let fully_qualified_name = Name::from(name).fully_qualified_name(enclosing_namespace);
             SerializationSchema::Enum {
+                aliases: to_aliases(aliases, &fully_qualified_name.namespace),
                 name: fully_qualified_name,
-                symbols: symbols
-                    .iter()
-                    .enumerate()
-                    .map(|(i, s)| (s.clone(), i))
-                    .collect(),
+                doc: doc.clone(),
+                // Symbols are only distinct in parsed schemas.
+                symbols: Symbols::new(symbols.clone())
+                    .map_err(|_| Error::EnumSymbolDuplicate(duplicate(symbols).unwrap().clone()))?,
+                default: default.clone(),
+                attributes: attributes.clone(),
             }
         }
-        Schema::Fixed { name, size, .. } => {
-            let fully_qualified_name = name.fully_qualified_name(enclosing_namespace);
+        Schema::Fixed {
+            name,
+            aliases,
+            doc,
+            size,
+            attributes,
+            ..
+        } => {
+            let fully_qualified_name = Name::from(name).fully_qualified_name(enclosing_namespace);
             SerializationSchema::Fixed {
+                aliases: to_aliases(aliases, &fully_qualified_name.namespace),
                 name: fully_qualified_name,
+                doc: doc.clone(),
                 size: *size,
+                attributes: attributes.clone(),
             }
         }
         Schema::Decimal {
@@ -154,11 +375,7 @@ pub fn to_serialization_schema(
         } => SerializationSchema::Decimal {
             precision: *precision,
             scale: *scale,
-            inner: Box::new(to_serialization_schema(
-                inner,
-                ref_indexes,
-                enclosing_namespace,
-            )),
+            inner: Box::new(to_serialization_schema(inner, enclosing_namespace)?),
         },
         Schema::Uuid => SerializationSchema::Uuid,
         Schema::Date => SerializationSchema::Date,
@@ -166,42 +383,123 @@ pub fn to_serialization_schema(
         Schema::TimeMicros => SerializationSchema::TimeMicros,
         Schema::TimestampMillis => SerializationSchema::TimestampMillis,
         Schema::TimestampMicros => SerializationSchema::TimestampMicros,
-        Schema::Duration => SerializationSchema::Duration,
-        Schema::Ref { name } => {
-            let fully_qualified_name = name.fully_qualified_name(enclosing_namespace);
-            let nb_refs = ref_indexes.len();
-            let resolved_index = *ref_indexes
-                .entry(fully_qualified_name.clone())
-                .or_insert(nb_refs);
-            SerializationSchema::Ref {
-                name: fully_qualified_name,
-                index: resolved_index,
+        // apache_avro doesn't keep the underlying fixed, so use the name it serializes.
+        Schema::Duration => SerializationSchema::Duration {
+            inner: Box::new(SerializationSchema::Fixed {
+                name: Name::new("duration"),
+                aliases: Vec::new(),
+                doc: None,
+                size: 12,
+                attributes: BTreeMap::new(),
+            }),
+        },
+        Schema::Ref { name } => SerializationSchema::Ref {
+            name: Name::from(name).fully_qualified_name(enclosing_namespace),
+        },
+    })
+}
+
+/// First symbol found twice.
+fn duplicate(symbols: &[String]) -> Option<&String> {
+    let mut seen = HashSet::new();
+    symbols.iter().find(|symbol| !seen.insert(*symbol))
+}
+
+/// Aliases are qualified by the namespace of the type they alias.
+#[cfg(feature = "apache-avro")]
+fn to_aliases(aliases: &Aliases, namespace: &Namespace) -> Vec<Name> {
+    aliases
+        .iter()
+        .flatten()
+        .map(|alias| {
+            Name {
+                name: alias.name(),
+                namespace: alias.namespace(),
             }
-        }
+            .fully_qualified_name(namespace)
+        })
+        .collect()
+}
+
+/// Named types defined in the schemas, by name and alias, the last definition of a name taking
+/// precedence; decimals and durations are defined by the name of their underlying fixed, so that
+/// references to it keep the logical type.
+pub(crate) fn definitions<'a>(
+    schemas: impl IntoIterator<Item = &'a SerializationSchema>,
+) -> HashMap<&'a Name, &'a SerializationSchema> {
+    let mut definitions = HashMap::new();
+    for schema in schemas {
+        define(schema, &mut definitions);
     }
+    definitions
 }
 
-pub fn set_refs<'a>(
+fn define<'a>(
     schema: &'a SerializationSchema,
-    ref_indexes: &HashMap<Name, usize>,
-    refs: &mut [Option<&'a SerializationSchema>],
+    definitions: &mut HashMap<&'a Name, &'a SerializationSchema>,
 ) {
-    match schema {
+    let named = match schema {
         SerializationSchema::Array(schema) | SerializationSchema::Map(schema) => {
-            set_refs(schema, ref_indexes, refs)
+            return define(schema, definitions);
         }
-        SerializationSchema::Union { schemas, .. } => {
-            schemas.iter().for_each(|s| set_refs(s, ref_indexes, refs))
+        SerializationSchema::Union { schemas } => {
+            return schemas.iter().for_each(|s| define(s, definitions));
         }
-        SerializationSchema::Record { name, .. }
-        | SerializationSchema::Enum { name, .. }
-        | SerializationSchema::Fixed { name, .. } => {
-            if let Some(&index) = ref_indexes.get(name) {
-                refs[index] = Some(schema)
-            }
+        SerializationSchema::Decimal { inner, .. } | SerializationSchema::Duration { inner } => {
+            inner
         }
-        _ => {}
+        SerializationSchema::Ref { .. } => return,
+        _ => schema,
+    };
+    for name in named.name().into_iter().chain(named.aliases()) {
+        definitions.insert(name, schema);
+    }
+    if let SerializationSchema::Record { fields, .. } = schema {
+        fields.iter().for_each(|f| define(&f.schema, definitions));
+    }
+}
+
+impl SerializationSchema {
+    pub fn name(&self) -> Option<&Name> {
+        match self {
+            SerializationSchema::Record { name, .. }
+            | SerializationSchema::Enum { name, .. }
+            | SerializationSchema::Fixed { name, .. }
+            | SerializationSchema::Ref { name } => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn aliases(&self) -> &[Name] {
+        match self {
+            SerializationSchema::Record { aliases, .. }
+            | SerializationSchema::Enum { aliases, .. }
+            | SerializationSchema::Fixed { aliases, .. } => aliases,
+            _ => &[],
+        }
+    }
+
+    /// Whether the schema is a named type matching `s`, by its name or one of its aliases.
+    pub fn is_named(&self, s: &str) -> bool {
+        self.name()
+            .into_iter()
+            .chain(self.aliases())
+            .any(|name| name_matches(name, s))
+    }
+}
+
+/// Match either the simple or the fully qualified name.
+pub fn name_matches(name: &Name, s: &str) -> bool {
+    if name.name == s {
+        return true;
     }
+    let Some(namespace) = &name.namespace else {
+        return false;
+    };
+    s.len() == namespace.len() + 1 + name.name.len()
+        && s.starts_with(namespace.as_str())
+        && s[namespace.len()..].starts_with('.')
+        && s.ends_with(name.name.as_str())
 }
 
 impl AsRef<[SerializationSchemaKind]> for SerializationSchemaKind {
@@ -1,25 +1,70 @@
-use std::io;
+use std::{io, mem, ops::Range, ptr};
 
 use integer_encoding::{VarInt, VarIntWriter};
-use serde::Serialize;
+use serde::{ser::Impossible, Serialize};
 
 use crate::{
-    error::SerializationError,
-    schema::{SerializationSchema, SerializationSchemaKind},
-    utils::StringChecker,
+    config::{SerializerConfig, VariantRepresentation},
+    error::{PathSegment, SerializationError},
+    logical,
+    node::{CompiledSchema, Field, FieldPlan, FieldPlans, Node, NodeId},
+    schema::{SerializationSchemaKind, Symbols},
+    utils::{key_string, FieldChecker, StringChecker},
+    value::UNION_BRANCH,
 };
 
 pub(crate) struct SerializerRef<'a, W> {
     pub(crate) writer: W,
-    pub(crate) schema: &'a SerializationSchema,
-    pub(crate) refs: &'a [&'a SerializationSchema],
+    pub(crate) schema: &'a Node,
+    pub(crate) nodes: &'a [Node],
+    pub(crate) plans: &'a [FieldPlans],
+    pub(crate) config: &'a SerializerConfig,
+    /// Errors collected so far, when serialization goes on after an error to report every error.
+    pub(crate) errors: Option<Vec<SerializationError>>,
+    /// Record fields serialized before the fields preceding them, with their field index and
+    /// their bytes in `buffer`.
+    pending: Vec<(usize, Range<usize>)>,
+    /// Bytes of the pending fields.
+    buffer: Vec<u8>,
+    /// Keys of the records whose field plan is being built, with their field index.
+    keys: Vec<(&'static str, usize)>,
+}
+
+pub(crate) enum Tagged<'a> {
+    /// Content field of an adjacently tagged variant.
+    Content(&'a Field),
+    /// Fields following the tag of an internally tagged variant.
+    Fields(&'a [Field]),
 }
 
 impl<'a, W> SerializerRef<'a, W> {
-    fn with_schema(&mut self, schema: &'a SerializationSchema) -> &mut Self {
+    pub(crate) fn new(writer: W, schema: &'a CompiledSchema, config: &'a SerializerConfig) -> Self {
+        Self {
+            writer,
+            schema: schema.root_node(),
+            nodes: &schema.nodes,
+            plans: &schema.plans,
+            config,
+            errors: None,
+            pending: Vec::new(),
+            buffer: Vec::new(),
+            keys: Vec::new(),
+        }
+    }
+
+    fn with_schema(&mut self, schema: &'a Node) -> &mut Self {
         self.schema = schema;
         self
     }
+
+    fn with_node(&mut self, id: NodeId) -> &mut Self {
+        self.schema = self.node(id);
+        self
+    }
+
+    fn node(&self, id: NodeId) -> &'a Node {
+        &self.nodes[id as usize]
+    }
 }
 
 impl<'a, W> SerializerRef<'a, W>
@@ -27,19 +72,7 @@ where
     W: io::Write,
 {
     pub(crate) fn serialize(&mut self, value: impl Serialize) -> Result<(), SerializationError> {
-        match self.schema {
-            SerializationSchema::Ref { index, .. } => {
-                let schema = self.refs[*index];
-                self.with_schema(schema).serialize(value)
-            }
-            SerializationSchema::Union {
-                schemas,
-                variant_index,
-            } if schemas.len() != variant_index.len() => {
-                todo!()
-            }
-            _ => value.serialize(self),
-        }
+        value.serialize(self)
     }
 
     fn write(&mut self, bytes: &[u8]) -> Result<(), SerializationError> {
@@ -59,14 +92,56 @@ where
 
     fn write_tag(
         &mut self,
-        fields: &'a [(String, SerializationSchema)],
+        fields: &'a [Field],
+        variant: &'static str,
+    ) -> Result<Tagged<'a>, SerializationError> {
+        let (tag, tagged) = tag_fields(self.config, fields)?;
+        self.with_node(tag.node).serialize(variant)?;
+        Ok(tagged)
+    }
+
+    /// Select the union branch named after the variant, see [`variant_branch`].
+    fn write_branch(
+        &mut self,
+        branches: &'a [NodeId],
+        variant: &'static str,
+    ) -> Result<Option<(usize, &'a Node)>, SerializationError> {
+        let Some((index, schema)) = variant_branch(self.nodes, branches, variant) else {
+            return Ok(None);
+        };
+        self.write_varint(index as i64)?;
+        Ok(Some((index, schema)))
+    }
+
+    /// Select the union branch of a unit variant, see [`unit_variant_branch`].
+    fn write_unit_branch(
+        &mut self,
+        branches: &'a [NodeId],
+        variant_index: u32,
         variant: &'static str,
-    ) -> Result<&'a SerializationSchema, SerializationError> {
-        if fields.len() != 2 || fields[0].0 != "type" || fields[1].0 != "value" {
-            return Err("tag record must have two fields: \"type\" and \"value\"".into());
+    ) -> Result<bool, SerializationError> {
+        let Some((index, symbol)) =
+            unit_variant_branch(self.nodes, branches, variant_index, variant)?
+        else {
+            return Ok(false);
+        };
+        self.write_varint(index as i64)?;
+        if let Some(symbol) = symbol {
+            self.write_varint(symbol as i64)?;
+        }
+        Ok(true)
+    }
+
+    /// Write the two's complement bytes of a decimal, sign extended for a fixed.
+    fn write_decimal(&mut self, inner: NodeId, bytes: &[u8]) -> Result<(), SerializationError> {
+        match self.node(inner) {
+            Node::Fixed { size, .. } => {
+                let bytes = logical::sign_extend(bytes, *size)
+                    .ok_or_else(|| format!("decimal doesn't fit in fixed {size}"))?;
+                self.write(&bytes)
+            }
+            _ => self.write_bytes(bytes),
         }
-        self.with_schema(&fields[0].1).serialize(variant)?;
-        Ok(&fields[1].1)
     }
 
     fn collection<'b>(
@@ -78,27 +153,100 @@ where
         };
         self.write_varint(len as i64)?;
         Ok(CollectionSerializer {
+            schema: self.schema,
+            errors_start: self.errors_len(),
             serializer: self,
             empty: len == 0,
+            index: 0,
+            branch: None,
         })
     }
 
+    /// Serialize a record, with the field plans of its schema if the type name is static.
     fn record<'b>(
         &'b mut self,
         name: &'static str,
-        fields: &'a [(String, SerializationSchema)],
+        fields: &'a [Field],
+        plans: Option<u32>,
     ) -> RecordSerializer<'a, 'b, W> {
+        let plans = plans.map(|index| &self.plans[index as usize]);
+        let plan = plans.and_then(|plans| plans.get(name));
         RecordSerializer {
+            errors_start: self.errors_len(),
+            pending_start: self.pending.len(),
+            keys_start: self.keys.len(),
             serializer: self,
             type_name: name,
             fields,
+            plan,
+            plans: plans.filter(|_| plan.is_none()),
+            next: 0,
+            len: 0,
+            branch: None,
+        }
+    }
+
+    fn errors_len(&self) -> usize {
+        self.errors.as_ref().map_or(0, Vec::len)
+    }
+
+    /// When collecting errors, the error is recorded and serialization goes on; only called with
+    /// errors, to keep the error path out of the way.
+    #[cold]
+    fn collect(
+        &mut self,
+        result: Result<(), SerializationError>,
+    ) -> Result<(), SerializationError> {
+        match (result, &mut self.errors) {
+            (Err(error), Some(errors)) => {
+                errors.extend(error.into_errors());
+                Ok(())
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Errors collected while serializing a compound value, which are returned so that the
+    /// enclosing values add their path.
+    fn collected(&mut self, start: usize) -> Result<(), SerializationError> {
+        match &mut self.errors {
+            Some(errors) if errors.len() > start => {
+                SerializationError::from_errors(errors.drain(start..).collect())
+            }
+            _ => Ok(()),
         }
     }
 }
 
 pub(crate) struct CollectionSerializer<'a, 'b, W> {
     serializer: &'b mut SerializerRef<'a, W>,
+    /// Item schema, as the serializer schema is changed when serializing an item.
+    schema: &'a Node,
     empty: bool,
+    /// Index of the next item or entry.
+    index: usize,
+    branch: Option<usize>,
+    /// Collected errors before this index are not the ones of the value.
+    errors_start: usize,
+}
+
+impl<'a, 'b, W> CollectionSerializer<'a, 'b, W>
+where
+    W: io::Write,
+{
+    fn serialize_item<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+        segment: impl FnOnce() -> PathSegment,
+    ) -> Result<(), SerializationError> {
+        let result = self.serializer.with_schema(self.schema).serialize(value);
+        self.index += 1;
+        if result.is_ok() {
+            return Ok(());
+        }
+        let result = result.with_segment(segment).with_branch(self.branch);
+        self.serializer.collect(result)
+    }
 }
 
 impl<'a, 'b, W> serde::ser::SerializeSeq for CollectionSerializer<'a, 'b, W>
@@ -108,18 +256,16 @@ where
     type Ok = ();
     type Error = SerializationError;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
-    where
-        T: Serialize,
-    {
-        self.serializer.serialize(value)
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let index = self.index;
+        self.serialize_item(value, || PathSegment::Index(index))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
         if !self.empty {
             self.serializer.write(&[0])?;
         }
-        Ok(())
+        self.serializer.collected(self.errors_start)
     }
 }
 
@@ -130,10 +276,7 @@ where
     type Ok = ();
     type Error = SerializationError;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
-    where
-        T: Serialize,
-    {
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
         serde::ser::SerializeSeq::serialize_element(self, value)
     }
 
@@ -149,19 +292,32 @@ where
     type Ok = ();
     type Error = SerializationError;
 
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Self::Error>
-    where
-        T: Serialize,
-    {
-        key.serialize(StringChecker)?;
-        self.serializer.serialize(key)
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        let result = key
+            .serialize(StringChecker)
+            .and_then(|_| self.serializer.with_schema(&Node::String).serialize(key));
+        if result.is_ok() {
+            return Ok(());
+        }
+        let index = self.index;
+        let result = result
+            .with_segment(|| PathSegment::Index(index))
+            .with_branch(self.branch);
+        self.serializer.collect(result)
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
-    where
-        T: Serialize,
-    {
-        serde::ser::SerializeSeq::serialize_element(self, value)
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let index = self.index;
+        self.serialize_item(value, || PathSegment::Index(index))
+    }
+
+    fn serialize_entry<K: ?Sized + Serialize, V: ?Sized + Serialize>(
+        &mut self,
+        key: &K,
+        value: &V,
+    ) -> Result<(), Self::Error> {
+        self.serialize_key(key)?;
+        self.serialize_item(value, || PathSegment::Key(key_string(key)))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -169,10 +325,202 @@ where
     }
 }
 
+/// Serializes either a map, or a record from entries keyed by field name, e.g. dynamic values.
+pub(crate) enum MapSerializer<'a, 'b, W> {
+    Map(CollectionSerializer<'a, 'b, W>),
+    Record(RecordSerializer<'a, 'b, W>),
+}
+
+impl<'a, 'b, W> serde::ser::SerializeMap for MapSerializer<'a, 'b, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = SerializationError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        match self {
+            MapSerializer::Map(map) => map.serialize_key(key),
+            MapSerializer::Record(record) => {
+                let result = match record.fields.get(record.next) {
+                    Some(field) => key.serialize(FieldChecker(&field.name)),
+                    None => Err("unexpected field".into()),
+                };
+                if result.is_ok() {
+                    return Ok(());
+                }
+                let result = result
+                    .with_segment(|| PathSegment::Key(key_string(key)))
+                    .with_branch(record.branch);
+                record.serializer.collect(result)
+            }
+        }
+    }
+
+    fn serialize_entry<K: ?Sized + Serialize, V: ?Sized + Serialize>(
+        &mut self,
+        key: &K,
+        value: &V,
+    ) -> Result<(), Self::Error> {
+        match self {
+            MapSerializer::Map(map) => map.serialize_entry(key, value),
+            MapSerializer::Record(_) => {
+                self.serialize_key(key)?;
+                self.serialize_value(value)
+            }
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        match self {
+            MapSerializer::Map(map) => map.serialize_value(value),
+            MapSerializer::Record(record) => {
+                let Some(field) = record.fields.get(record.next) else {
+                    // The unexpected field has already been reported by its key.
+                    return Ok(());
+                };
+                record.next += 1;
+                let result = record.serializer.with_node(field.node).serialize(value);
+                if result.is_ok() {
+                    return Ok(());
+                }
+                let result = result
+                    .with_segment(|| PathSegment::Key(field.name.clone()))
+                    .with_branch(record.branch);
+                record.serializer.collect(result)
+            }
+        }
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        match self {
+            MapSerializer::Map(map) => serde::ser::SerializeMap::end(map),
+            MapSerializer::Record(record) => serde::ser::SerializeStruct::end(record),
+        }
+    }
+}
+
+/// Serializes a record from struct fields, which are written in schema order whatever the order
+/// of the struct fields.
 pub(crate) struct RecordSerializer<'a, 'b, W> {
     serializer: &'b mut SerializerRef<'a, W>,
     type_name: &'static str,
-    fields: &'a [(String, SerializationSchema)],
+    fields: &'a [Field],
+    /// Field plan of the type, giving the field index of its keys without comparing them.
+    plan: Option<&'a FieldPlan>,
+    /// Plans to which the plan of the type is added once its fields are validated.
+    plans: Option<&'a FieldPlans>,
+    /// Index of the next field to write.
+    next: usize,
+    /// Number of serialized keys.
+    len: usize,
+    branch: Option<usize>,
+    /// Collected errors before this index are not the ones of the value.
+    errors_start: usize,
+    /// Pending fields before this index are not the ones of the value.
+    pending_start: usize,
+    /// Keys before this index are not the ones of the value.
+    keys_start: usize,
+}
+
+impl<W> RecordSerializer<'_, '_, W>
+where
+    W: io::Write,
+{
+    /// Field index from the plan if the key is the one of the plan, otherwise from field names,
+    /// the plan being dropped once the keys don't follow it.
+    fn field_index(&mut self, key: &'static str) -> Option<usize> {
+        if let Some(plan) = self.plan {
+            match plan.keys.get(self.len) {
+                Some(&(plan_key, index)) if ptr::eq(plan_key, key) => return Some(index),
+                _ => self.plan = None,
+            }
+        }
+        if self
+            .fields
+            .get(self.next)
+            .is_some_and(|field| field.name == key)
+        {
+            return Some(self.next);
+        }
+        self.find_field(key)
+    }
+
+    #[cold]
+    fn find_field(&self, key: &str) -> Option<usize> {
+        self.fields.iter().position(|field| field.name == key)
+    }
+
+    fn is_pending(&self, index: usize) -> bool {
+        self.serializer.pending[self.pending_start..]
+            .iter()
+            .any(|(i, _)| *i == index)
+    }
+
+    /// Whether a field was serialized before fields preceding it.
+    fn is_buffered(&self) -> bool {
+        self.serializer.pending.len() > self.pending_start
+    }
+
+    /// Serialize a field following fields not written yet into the buffer, kept until they are.
+    #[inline(never)]
+    fn defer<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        index: Option<usize>,
+        value: &T,
+    ) -> Result<(), SerializationError> {
+        let index = match index {
+            // Keys following the plan are distinct.
+            Some(index) if self.plan.is_some() => index,
+            Some(index) if index > self.next && !self.is_pending(index) => index,
+            Some(_) => return Err(format!("duplicate field {key}").into()),
+            None => return Err("unexpected field".into()),
+        };
+        let mut buffer = mem::take(&mut self.serializer.buffer);
+        let start = buffer.len();
+        let mut serializer = SerializerRef {
+            writer: &mut buffer,
+            schema: self.serializer.node(self.fields[index].node),
+            nodes: self.serializer.nodes,
+            plans: self.serializer.plans,
+            config: self.serializer.config,
+            errors: self.serializer.errors.take(),
+            pending: Vec::new(),
+            buffer: Vec::new(),
+            keys: Vec::new(),
+        };
+        let result = serializer.serialize(value);
+        self.serializer.errors = serializer.errors;
+        self.serializer.pending.push((index, start..buffer.len()));
+        self.serializer.buffer = buffer;
+        result
+    }
+
+    /// Write the pending fields following the written ones.
+    fn write_pending(&mut self) -> Result<(), SerializationError> {
+        let serializer = &mut *self.serializer;
+        let pending = &serializer.pending[self.pending_start..];
+        // Every key from the first pending field has an entry, so the plan gives the entry of a
+        // field from the position of its key.
+        let first = self.len - pending.len();
+        let order = self.plan.and_then(|plan| plan.order.as_deref());
+        loop {
+            let entry = match order {
+                Some(order) => order
+                    .get(self.next)
+                    .and_then(|position| pending.get(position - first)),
+                None => pending.iter().find(|(index, _)| *index == self.next),
+            };
+            let Some((_, range)) = entry else {
+                return Ok(());
+            };
+            serializer
+                .writer
+                .write_all(&serializer.buffer[range.clone()])?;
+            self.next += 1;
+        }
+    }
 }
 
 impl<'a, 'b, W> serde::ser::SerializeStruct for RecordSerializer<'a, 'b, W>
@@ -182,39 +530,271 @@ where
     type Ok = ();
     type Error = SerializationError;
 
-    fn serialize_field<T: ?Sized>(
+    fn serialize_field<T: ?Sized + Serialize>(
         &mut self,
         key: &'static str,
         value: &T,
-    ) -> Result<(), Self::Error>
-    where
-        T: Serialize,
-    {
-        if self.fields.is_empty() {
-            return Err("unexpected field".into()).with_path(self.type_name, key);
-        }
-        if key != self.fields[0].0 {
-            return Err(format!("expected field {}", self.fields[0].0).into())
-                .with_path(self.type_name, key);
-        }
-        self.serializer
-            .with_schema(&self.fields[0].1)
-            .serialize(value)
-            .with_path(self.type_name, key)?;
-        self.fields = &self.fields[1..];
-        Ok(())
+    ) -> Result<(), Self::Error> {
+        let index = self.field_index(key);
+        self.len += 1;
+        if let (Some(index), Some(_)) = (index, self.plans) {
+            self.serializer.keys.push((key, index));
+        }
+        let result = if index == Some(self.next) {
+            let node = self.fields[self.next].node;
+            self.next += 1;
+            if self.is_buffered() {
+                // An empty entry keeps the entries in key order.
+                let end = self.serializer.buffer.len();
+                self.serializer.pending.push((self.next - 1, end..end));
+                let result = self.serializer.with_node(node).serialize(value);
+                result.and_then(|()| self.write_pending())
+            } else {
+                self.serializer.with_node(node).serialize(value)
+            }
+        } else {
+            self.defer(key, index, value)
+        };
+        if result.is_ok() {
+            return Ok(());
+        }
+        let result = result
+            .with_path(self.type_name, key)
+            .with_branch(self.branch);
+        self.serializer.collect(result)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        if !self.fields.is_empty() {
-            return Err(format!("missing field {}", self.fields[0].0).into())
-                .with_path(self.type_name, "");
+        if self.is_buffered() {
+            let start = self.serializer.pending[self.pending_start].1.start;
+            self.serializer.buffer.truncate(start);
+            self.serializer.pending.truncate(self.pending_start);
+        }
+        if let Some(field) = self.fields.get(self.next) {
+            let result = Err(format!("missing field {}", field.name).into())
+                .with_path(self.type_name, "")
+                .with_branch(self.branch);
+            self.serializer.collect(result)?;
         }
+        if let Some(plans) = self.plans {
+            if self.serializer.errors_len() == self.errors_start {
+                plans.insert(self.type_name, &self.serializer.keys[self.keys_start..]);
+            }
+            self.serializer.keys.truncate(self.keys_start);
+        }
+        self.serializer.collected(self.errors_start)
+    }
+}
+
+/// Serializes the content of an internally tagged variant, which must be a struct, into the
+/// record fields following the tag.
+pub(crate) struct ContentSerializer<C>(pub(crate) C);
+
+/// Record of the fields following the tag, in the binary or the JSON encoding.
+pub(crate) trait Content {
+    type Ok;
+    type Record: serde::ser::SerializeStruct<Ok = Self::Ok, Error = SerializationError>;
+
+    fn fields(&self) -> &[Field];
+
+    fn record(self, name: &'static str) -> Self::Record;
+
+    /// Value of a record without fields following the tag.
+    fn unit(self) -> Result<Self::Ok, SerializationError>;
+}
+
+struct BinaryContent<'a, 'b, W> {
+    serializer: &'b mut SerializerRef<'a, W>,
+    fields: &'a [Field],
+}
+
+impl<'a, 'b, W> Content for BinaryContent<'a, 'b, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Record = RecordSerializer<'a, 'b, W>;
+
+    fn fields(&self) -> &[Field] {
+        self.fields
+    }
+
+    fn record(self, name: &'static str) -> Self::Record {
+        self.serializer.record(name, self.fields, None)
+    }
+
+    fn unit(self) -> Result<(), SerializationError> {
         Ok(())
     }
 }
 
-trait WithPath {
+fn not_a_struct<Ok>() -> Result<Ok, SerializationError> {
+    Err("internally tagged variant content must be a struct".into())
+}
+
+impl<C: Content> serde::Serializer for ContentSerializer<C> {
+    type Ok = C::Ok;
+    type Error = SerializationError;
+    type SerializeSeq = Impossible<C::Ok, SerializationError>;
+    type SerializeTuple = Impossible<C::Ok, SerializationError>;
+    type SerializeTupleStruct = Impossible<C::Ok, SerializationError>;
+    type SerializeTupleVariant = Impossible<C::Ok, SerializationError>;
+    type SerializeMap = Impossible<C::Ok, SerializationError>;
+    type SerializeStruct = C::Record;
+    type SerializeStructVariant = Impossible<C::Ok, SerializationError>;
+
+    fn serialize_bool(self, _: bool) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_i8(self, _: i8) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_i16(self, _: i16) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_i32(self, _: i32) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_u8(self, _: u8) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_u16(self, _: u16) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_u32(self, _: u32) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_char(self, _: char) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_str(self, _: &str) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        match self.0.fields().first() {
+            Some(field) => Err(format!("missing field {}", field.name).into()),
+            None => self.0.unit(),
+        }
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self).with_path(name, "")
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        not_a_struct()
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(self.0.record(name))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        not_a_struct()
+    }
+}
+
+pub(crate) trait WithPath {
     type Output;
     fn with_path(self, type_name: &'static str, field: &'static str) -> Self::Output;
 }
@@ -222,24 +802,206 @@ trait WithPath {
 impl<T> WithPath for Result<T, SerializationError> {
     type Output = Result<T, SerializationError>;
     fn with_path(mut self, type_name: &'static str, field: &'static str) -> Self::Output {
-        if let Err(
-            SerializationError::SchemaMismatch { ref mut path, .. }
-            | SerializationError::Custom { ref mut path, .. },
-        ) = self
-        {
+        if let Err(error) = &mut self {
             if !field.is_empty() {
-                path.push_front(field)
+                push_segment(error, &PathSegment::Field(field));
+            }
+            if !type_name.is_empty() {
+                push_segment(error, &PathSegment::Type(type_name));
             }
-            path.push_front(type_name);
         }
         self
     }
 }
 
+pub(crate) trait WithSegment: Sized {
+    /// Prepend the segment to the path of the error; it is only built in case of error.
+    fn with_segment(self, segment: impl FnOnce() -> PathSegment) -> Self;
+
+    fn with_branch(self, branch: Option<usize>) -> Self;
+}
+
+impl<T> WithSegment for Result<T, SerializationError> {
+    fn with_segment(mut self, segment: impl FnOnce() -> PathSegment) -> Self {
+        if let Err(error) = &mut self {
+            push_segment(error, &segment());
+        }
+        self
+    }
+
+    fn with_branch(mut self, branch: Option<usize>) -> Self {
+        if let (Err(error), Some(index)) = (&mut self, branch) {
+            push_segment(error, &PathSegment::Branch(index));
+        }
+        self
+    }
+}
+
+#[cold]
+fn push_segment(error: &mut SerializationError, segment: &PathSegment) {
+    match error {
+        SerializationError::SchemaMismatch { path, .. }
+        | SerializationError::Custom { path, .. } => path.push_front(segment.clone()),
+        SerializationError::Multiple(errors) => errors
+            .iter_mut()
+            .for_each(|error| push_segment(error, segment)),
+        SerializationError::Io(_)
+        | SerializationError::BufferTooSmall { .. }
+        | SerializationError::Json(_) => {}
+    }
+}
+
+/// Serializers returned for a union branch keep its index, to add it to the path of their errors.
+pub(crate) trait SetBranch {
+    fn set_branch(&mut self, index: usize);
+}
+
+impl SetBranch for () {
+    fn set_branch(&mut self, _: usize) {}
+}
+
+impl<W> SetBranch for CollectionSerializer<'_, '_, W> {
+    fn set_branch(&mut self, index: usize) {
+        self.branch = Some(index);
+    }
+}
+
+impl<W> SetBranch for RecordSerializer<'_, '_, W> {
+    fn set_branch(&mut self, index: usize) {
+        self.branch = Some(index);
+    }
+}
+
+impl<W> SetBranch for MapSerializer<'_, '_, W> {
+    fn set_branch(&mut self, index: usize) {
+        match self {
+            MapSerializer::Map(map) => map.set_branch(index),
+            MapSerializer::Record(record) => record.set_branch(index),
+        }
+    }
+}
+
+impl<S> SetBranch for WithPathSerializer<S> {
+    fn set_branch(&mut self, index: usize) {
+        self.branch = Some(index);
+    }
+}
+
+/// Symbol of a unit variant, which is at the serde variant index when the Rust enum variants
+/// match the schema symbols, so that the lookup can most often be skipped.
+fn symbol_index(symbols: &Symbols, variant_index: u32, variant: &str) -> Option<usize> {
+    match symbols.get(variant_index as usize) {
+        Some(symbol) if symbol == variant => Some(variant_index as usize),
+        _ => symbols.position(variant),
+    }
+}
+
+/// Tag field of the record a variant is serialized into, followed by the field of its content or
+/// by the fields of its content, depending on the variant representation.
+pub(crate) fn tag_fields<'a>(
+    config: &SerializerConfig,
+    fields: &'a [Field],
+) -> Result<(&'a Field, Tagged<'a>), SerializationError> {
+    match &config.variant_representation {
+        VariantRepresentation::AdjacentlyTagged { tag, content } => {
+            if fields.len() != 2 || fields[0].name != *tag || fields[1].name != *content {
+                return Err(format!(
+                    "tag record must have two fields: \"{tag}\" and \"{content}\""
+                )
+                .into());
+            }
+            Ok((&fields[0], Tagged::Content(&fields[1])))
+        }
+        VariantRepresentation::InternallyTagged { tag } => {
+            if fields.first().is_none_or(|field| field.name != *tag) {
+                return Err(format!("tag record must have \"{tag}\" as first field").into());
+            }
+            Ok((&fields[0], Tagged::Fields(&fields[1..])))
+        }
+        VariantRepresentation::Union => Err("variant must be serialized as union branch".into()),
+    }
+}
+
+/// Union branch named after a variant, either by named type or by kind.
+pub(crate) fn variant_branch<'a>(
+    nodes: &'a [Node],
+    branches: &[NodeId],
+    variant: &str,
+) -> Option<(usize, &'a Node)> {
+    let branches = || branches.iter().map(|id| &nodes[*id as usize]).enumerate();
+    branches().find(|(_, s)| s.is_named(variant)).or_else(|| {
+        branches().find(|(_, s)| <&str>::from(SerializationSchemaKind::from(*s)) == variant)
+    })
+}
+
+/// Union branch of a unit variant, with its symbol: unit variants go to an enum branch having the
+/// variant as symbol, or to the null branch when the union has no enum branch or the variant is
+/// named `null`.
+pub(crate) fn unit_variant_branch(
+    nodes: &[Node],
+    branches: &[NodeId],
+    variant_index: u32,
+    variant: &str,
+) -> Result<Option<(usize, Option<usize>)>, SerializationError> {
+    let mut has_enum = false;
+    for (index, id) in branches.iter().enumerate() {
+        let Node::Enum { symbols, .. } = &nodes[*id as usize] else {
+            continue;
+        };
+        if let Some(symbol) = symbol_index(symbols, variant_index, variant) {
+            return Ok(Some((index, Some(symbol))));
+        }
+        has_enum = true;
+    }
+    if has_enum && variant != "null" {
+        return Err(format!("unknown symbol {variant}").into());
+    }
+    Ok(branches
+        .iter()
+        .position(|id| matches!(nodes[*id as usize], Node::Null))
+        .map(|index| (index, None)))
+}
+
+/// Record branch of a union a struct is serialized into: the record named after the struct, or
+/// the only record.
+pub(crate) fn record_branch<'a>(
+    branches: impl Iterator<Item = &'a Node> + Clone,
+    name: &str,
+) -> Result<Option<(usize, &'a Node)>, SerializationError> {
+    let records = || {
+        branches
+            .clone()
+            .enumerate()
+            .filter(|(_, s)| matches!(s, Node::Record { .. }))
+    };
+    if let Some(branch) = records().find(|(_, s)| s.is_named(name)) {
+        return Ok(Some(branch));
+    }
+    match (records().next(), records().nth(1)) {
+        (Some(_), Some(_)) => Err(format!("no record branch named {name}").into()),
+        (branch, _) => Ok(branch),
+    }
+}
+
+/// Serialize in a union branch; serialization is run in a closure to also catch early returns.
+fn in_branch<T: SetBranch>(
+    serialize: impl FnOnce() -> Result<T, SerializationError>,
+    index: usize,
+) -> Result<T, SerializationError> {
+    match serialize() {
+        Ok(mut serializer) => {
+            serializer.set_branch(index);
+            Ok(serializer)
+        }
+        Err(error) => Err(error).with_segment(|| PathSegment::Branch(index)),
+    }
+}
+
 pub(crate) struct WithPathSerializer<S> {
     serializer: S,
     type_name: &'static str,
     field: &'static str,
+    branch: Option<usize>,
 }
 
 impl<'a, 'b, W> WithPath for &'b mut SerializerRef<'a, W> {
@@ -249,6 +1011,7 @@ impl<'a, 'b, W> WithPath for &'b mut SerializerRef<'a, W> {
             serializer: self,
             type_name,
             field,
+            branch: None,
         }
     }
 }
@@ -260,6 +1023,7 @@ impl<'a, 'b, W> WithPath for CollectionSerializer<'a, 'b, W> {
             serializer: self,
             type_name,
             field,
+            branch: None,
         }
     }
 }
@@ -271,6 +1035,7 @@ impl<'a, 'b, W> WithPath for RecordSerializer<'a, 'b, W> {
             serializer: self,
             type_name,
             field,
+            branch: None,
         }
     }
 }
@@ -283,16 +1048,16 @@ where
     type Ok = ();
     type Error = SerializationError;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
-    where
-        T: Serialize,
-    {
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
         serde::ser::SerializeSeq::serialize_element(&mut self.serializer, value)
             .with_path(self.type_name, self.field)
+            .with_branch(self.branch)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        serde::ser::SerializeSeq::end(self.serializer).with_path(self.type_name, self.field)
+        serde::ser::SerializeSeq::end(self.serializer)
+            .with_path(self.type_name, self.field)
+            .with_branch(self.branch)
     }
 }
 
@@ -304,16 +1069,16 @@ where
     type Ok = ();
     type Error = SerializationError;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
-    where
-        T: Serialize,
-    {
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
         serde::ser::SerializeSeq::serialize_element(&mut self.serializer, value)
             .with_path(self.type_name, self.field)
+            .with_branch(self.branch)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        serde::ser::SerializeSeq::end(self.serializer).with_path(self.type_name, self.field)
+        serde::ser::SerializeSeq::end(self.serializer)
+            .with_path(self.type_name, self.field)
+            .with_branch(self.branch)
     }
 }
 
@@ -325,50 +1090,81 @@ where
     type Ok = ();
     type Error = SerializationError;
 
-    fn serialize_field<T: ?Sized>(
+    fn serialize_field<T: ?Sized + Serialize>(
         &mut self,
         key: &'static str,
         value: &T,
-    ) -> Result<(), Self::Error>
-    where
-        T: Serialize,
-    {
+    ) -> Result<(), Self::Error> {
         serde::ser::SerializeStruct::serialize_field(&mut self.serializer, key, value)
             .with_path(self.type_name, self.field)
+            .with_branch(self.branch)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        serde::ser::SerializeStruct::end(self.serializer).with_path(self.type_name, self.field)
+        serde::ser::SerializeStruct::end(self.serializer)
+            .with_path(self.type_name, self.field)
+            .with_branch(self.branch)
     }
 }
 
 macro_rules! match_schema {
     ($self:expr, $($expected:ident),*; $stmt:expr $(;$kind2:ident, $expected2:pat => $stmt2:expr)*) => {{
         match $self.schema {
-            $(SerializationSchema::$expected => {#[allow(unreachable_code)] return $stmt;})*
+            $(Node::$expected => {#[allow(unreachable_code)] return $stmt;})*
             $($expected2 => {#[allow(unreachable_code)] return $stmt2;})*
+            // The two branches have distinct kinds, matched without looking up `variant_index`.
+            #[allow(unused_variables)]
+            Node::Nullable {branches, null_index} => {
+                for index in [*null_index, 1 - null_index] {
+                    match $self.node(branches[index]) {
+                        $(Node::$expected => {
+                            $self.writer.write_varint(index as i64)?;
+                            #[allow(unreachable_code)]
+                            return in_branch(move || $stmt, index);
+                        })*
+                        $($expected2 => {
+                            $self.writer.write_varint(index as i64)?;
+                            #[allow(unreachable_code)]
+                            return in_branch(move || $stmt2, index);
+                        })*
+                        _ => {}
+                    }
+                }
+            }
             #[allow(unused_variables)]
-            SerializationSchema::Union {schemas, variant_index} => {
+            Node::Union {branches, variant_index} => {
                 $(if let Some(index) = variant_index.get(&SerializationSchemaKind::$expected) {
                     $self.writer.write_varint(*index as i64)?;
                     #[allow(unreachable_code)]
-                    return $stmt;
+                    return in_branch(move || $stmt, *index);
                 })*
                 $(if let Some(index) = variant_index.get(&SerializationSchemaKind::$kind2) {
-                    match &schemas[*index] {
+                    match $self.node(branches[*index]) {
                         $expected2 => {
                             $self.writer.write_varint(*index as i64)?;
                             #[allow(unreachable_code)]
-                            return $stmt2;
+                            return in_branch(move || $stmt2, *index);
                         }
                         _ => {}
                     }
                 })*
+                // Named types sharing their kind with other branches are not in `variant_index`:
+                // the first branch of the expected kind is selected.
+                for (index, branch) in branches.iter().enumerate() {
+                    match $self.node(*branch) {
+                        $($expected2 => {
+                            $self.writer.write_varint(index as i64)?;
+                            #[allow(unreachable_code)]
+                            return in_branch(move || $stmt2, index);
+                        })*
+                        _ => {}
+                    }
+                }
             }
             _ => {}
         }
         return Err(SerializationError::SchemaMismatch {
-                expected: $self.schema.clone(),
+                expected: Box::new($self.schema.to_schema($self.nodes)),
                 found: [$(SerializationSchemaKind::$expected,)*$(SerializationSchemaKind::$kind2,)*][0],
                 path: Default::default(),
         });
@@ -385,7 +1181,7 @@ where
     type SerializeTuple = CollectionSerializer<'a, 'b, W>;
     type SerializeTupleStruct = WithPathSerializer<CollectionSerializer<'a, 'b, W>>;
     type SerializeTupleVariant = WithPathSerializer<CollectionSerializer<'a, 'b, W>>;
-    type SerializeMap = CollectionSerializer<'a, 'b, W>;
+    type SerializeMap = MapSerializer<'a, 'b, W>;
     type SerializeStruct = RecordSerializer<'a, 'b, W>;
     type SerializeStructVariant = WithPathSerializer<RecordSerializer<'a, 'b, W>>;
 
@@ -402,11 +1198,17 @@ where
     }
 
     fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
-        match_schema!(self, Int, Long; self.write_varint(v));
+        match_schema!(
+            self, Int, Long, Date, TimeMillis, TimeMicros, TimestampMillis, TimestampMicros;
+            self.write_varint(v)
+        );
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-        match_schema!(self, Long; self.write_varint(v));
+        match_schema!(
+            self, Long, TimeMicros, TimestampMillis, TimestampMicros;
+            self.write_varint(v)
+        );
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
@@ -438,12 +1240,37 @@ where
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        let invalid = |kind| format!("invalid {kind} {v}");
         match_schema!(
-            self, String;
+            self, String, Uuid;
             self.write_bytes(v.as_bytes());
-            Enum, SerializationSchema::Enum {symbols, ..} => {
-                let index = *symbols.get(v).ok_or_else(|| format!("unexpected {v} in enum"))?;
+            Enum, Node::Enum {symbols, ..} => {
+                let index = symbols.position(v).ok_or_else(|| format!("unexpected {v} in enum"))?;
                 self.write_varint(index as i64)
+            };
+            Date, Node::Date => {
+                self.write_varint(logical::parse_date(v).ok_or_else(|| invalid("date"))?)
+            };
+            TimeMillis, Node::TimeMillis => {
+                let nanos = logical::parse_time(v).ok_or_else(|| invalid("time"))?;
+                self.write_varint((nanos / 1_000_000) as i32)
+            };
+            TimeMicros, Node::TimeMicros => {
+                let nanos = logical::parse_time(v).ok_or_else(|| invalid("time"))?;
+                self.write_varint(nanos / 1_000)
+            };
+            TimestampMillis, Node::TimestampMillis => {
+                let nanos = logical::parse_timestamp(v).ok_or_else(|| invalid("timestamp"))?;
+                self.write_varint(nanos.div_euclid(1_000_000) as i64)
+            };
+            TimestampMicros, Node::TimestampMicros => {
+                let nanos = logical::parse_timestamp(v).ok_or_else(|| invalid("timestamp"))?;
+                self.write_varint(nanos.div_euclid(1_000) as i64)
+            };
+            Decimal, Node::Decimal {precision, scale, inner} => {
+                let bytes = logical::decimal_bytes(v, *precision, *scale)
+                    .ok_or_else(|| invalid("decimal"))?;
+                self.write_decimal(*inner, &bytes)
             }
         );
     }
@@ -452,23 +1279,44 @@ where
         match_schema!(
             self, Bytes, Uuid;
             self.write_bytes(v);
-            Fixed, SerializationSchema::Fixed {size, ..} => {
+            Fixed, Node::Fixed {size, ..} => {
                 if *size != v.len() {
                     return Err(format!("expected fixed {size}, found {}", v.len()).into())
                 }
                 self.write(v)
+            };
+            Decimal, Node::Decimal {inner, ..} => self.write_decimal(*inner, v);
+            Duration, Node::Duration { .. } => {
+                if v.len() != 12 {
+                    return Err(format!("expected duration of 12 bytes, found {}", v.len()).into())
+                }
+                self.write(v)
             }
         );
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        if let Node::Nullable { null_index, .. } = self.schema {
+            return self.write_varint(*null_index as i64);
+        }
         match_schema!(self, Null; Ok(()));
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
-    where
-        T: Serialize,
-    {
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        // Options are mostly serialized in nullable unions, whose other branch is selected
+        // without looking up the value kind; the value can't be null itself.
+        if let Node::Nullable {
+            branches,
+            null_index,
+        } = self.schema
+        {
+            let index = 1 - null_index;
+            self.write_varint(index as i64)?;
+            return self
+                .with_node(branches[index])
+                .serialize(value)
+                .with_segment(|| PathSegment::Branch(index));
+        }
         value.serialize(self)
     }
 
@@ -483,48 +1331,101 @@ where
     fn serialize_unit_variant(
         self,
         name: &'static str,
-        _: u32,
+        variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
+        match self.schema {
+            Node::Null => return Ok(()),
+            Node::Enum { symbols, .. } => {
+                if let Some(index) = symbol_index(symbols, variant_index, variant) {
+                    return self.write_varint(index as i64);
+                }
+            }
+            Node::Record { fields, .. } => {
+                return match self.write_tag(fields, variant).with_path(name, variant)? {
+                    Tagged::Content(content) => self.with_node(content.node).serialize(()),
+                    Tagged::Fields(fields) => ContentSerializer(BinaryContent {
+                        serializer: self,
+                        fields,
+                    })
+                    .serialize_unit(),
+                }
+                .with_path(name, variant);
+            }
+            _ => {}
+        }
+        if let Some(branches) = self.schema.branches() {
+            if self
+                .write_unit_branch(branches, variant_index, variant)
+                .with_path(name, variant)?
+            {
+                return Ok(());
+            }
+        }
         variant.serialize(self).with_path(name, variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
         self,
         name: &'static str,
         value: &T,
-    ) -> Result<Self::Ok, Self::Error>
-    where
-        T: Serialize,
-    {
+    ) -> Result<Self::Ok, Self::Error> {
         value.serialize(self).with_path(name, "")
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
         self,
         name: &'static str,
-        _: u32,
+        variant_index: u32,
         variant: &'static str,
         value: &T,
-    ) -> Result<Self::Ok, Self::Error>
-    where
-        T: Serialize,
-    {
-        match_schema!(self, ; (); Record, SerializationSchema::Record {fields, ..} => {
-            let schema = self.write_tag(fields, variant).with_path(name, variant)?;
-            self.with_schema(schema).serialize(value).with_path(name, variant)
+    ) -> Result<Self::Ok, Self::Error> {
+        if name == UNION_BRANCH {
+            let Some(branches) = self.schema.branches() else {
+                return self.serialize(value);
+            };
+            let branch = branches
+                .get(variant_index as usize)
+                .ok_or_else(|| format!("invalid union branch {variant_index}"))?;
+            self.write_varint(variant_index as i64)?;
+            return self
+                .with_node(*branch)
+                .serialize(value)
+                .with_segment(|| PathSegment::Branch(variant_index as usize));
+        }
+        if let Some(branches) = self.schema.branches() {
+            if let Some((index, schema)) = self
+                .write_branch(branches, variant)
+                .with_path(name, variant)?
+            {
+                return self
+                    .with_schema(schema)
+                    .serialize(value)
+                    .with_segment(|| PathSegment::Branch(index))
+                    .with_path(name, variant);
+            }
+        }
+        match_schema!(self, ; (); Record, Node::Record {fields, ..} => {
+            match self.write_tag(fields, variant).with_path(name, variant)? {
+                Tagged::Content(content) => self.with_node(content.node).serialize(value),
+                Tagged::Fields(fields) => value.serialize(ContentSerializer(BinaryContent {
+                    serializer: self,
+                    fields,
+                })),
+            }
+            .with_path(name, variant)
         });
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        match_schema!(self, ; (); Array, SerializationSchema::Array(schema) => {
-            self.with_schema(schema).collection(len)
+        match_schema!(self, ; (); Array, Node::Array(items) => {
+            self.with_node(*items).collection(len)
         });
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
-        match_schema!(self, ; (); Array, SerializationSchema::Array(schema) => {
-            self.with_schema(schema).collection(len)
+        match_schema!(self, ; (); Array, Node::Array(items) => {
+            self.with_node(*items).collection(len)
         });
     }
 
@@ -533,8 +1434,8 @@ where
         name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        match_schema!(self, ; (); Array, SerializationSchema::Array(schema) => {
-            Ok(self.with_schema(schema).collection(len)?.with_path(name, ""))
+        match_schema!(self, ; (); Array, Node::Array(items) => {
+            Ok(self.with_node(*items).collection(len)?.with_path(name, ""))
         });
     }
 
@@ -545,19 +1446,28 @@ where
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        match_schema!(self, ; (); Record, SerializationSchema::Record {fields, ..} => {
-            let schema = self.write_tag(fields, variant).with_path(name, variant)?;
-            let serializer = self.with_schema(schema);
-            match_schema!(serializer, ; (); Array, SerializationSchema::Array(schema) => {
-                Ok(serializer.with_schema(schema).collection(len)?.with_path(name, variant))
+        match_schema!(self, ; (); Record, Node::Record {fields, ..} => {
+            let Tagged::Content(content) = self.write_tag(fields, variant).with_path(name, variant)? else {
+                return Err("internally tagged tuple variant is not supported".into())
+                    .with_path(name, variant);
+            };
+            let serializer = self.with_node(content.node);
+            match_schema!(serializer, ; (); Array, Node::Array(items) => {
+                Ok(serializer.with_node(*items).collection(len)?.with_path(name, variant))
             });
         });
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        match_schema!(self, ; (); Map, SerializationSchema::Map(schema) => {
-            self.with_schema(schema).collection(len)
-        });
+        match_schema!(
+            self, ; ();
+            Map, Node::Map(values) => {
+                Ok(MapSerializer::Map(self.with_node(*values).collection(len)?))
+            };
+            Record, Node::Record {fields, ..} => {
+                Ok(MapSerializer::Record(self.record("", fields, None)))
+            }
+        );
     }
 
     fn serialize_struct(
@@ -565,8 +1475,27 @@ where
         name: &'static str,
         _: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        match_schema!(self, ; (); Record, SerializationSchema::Record {fields, ..} => {
-            Ok(self.record(name, fields))
+        if let Node::Union {
+            branches,
+            variant_index,
+            ..
+        } = self.schema
+        {
+            if !variant_index.contains_key(&SerializationSchemaKind::Record) {
+                let branches = branches.iter().map(|id| self.node(*id));
+                if let Some((index, schema)) = record_branch(branches, name).with_path(name, "")? {
+                    let Node::Record { fields, plans, .. } = schema else {
+                        unreachable!()
+                    };
+                    self.write_varint(index as i64)?;
+                    let mut record = self.record(name, fields, Some(*plans));
+                    record.set_branch(index);
+                    return Ok(record);
+                }
+            }
+        }
+        match_schema!(self, ; (); Record, Node::Record {fields, plans, ..} => {
+            Ok(self.record(name, fields, Some(*plans)))
         });
     }
 
@@ -577,11 +1506,29 @@ where
         variant: &'static str,
         _: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        match_schema!(self, ; (); Record, SerializationSchema::Record {fields, ..} => {
-            let schema = self.write_tag(fields, variant).with_path(name, variant)?;
-            let serializer = self.with_schema(schema);
-            match_schema!(serializer, ; (); Record, SerializationSchema::Record {fields, ..} => {
-                Ok(serializer.record(name, fields).with_path(name, variant))
+        if let Some(branches) = self.schema.branches() {
+            if let Some((index, schema)) = self
+                .write_branch(branches, variant)
+                .with_path(name, variant)?
+            {
+                let serializer = self.with_schema(schema);
+                match_schema!(serializer, ; (); Record, Node::Record {fields, plans, ..} => {
+                    let mut record = serializer.record(name, fields, Some(*plans));
+                    record.set_branch(index);
+                    Ok(record.with_path(name, variant))
+                });
+            }
+        }
+        match_schema!(self, ; (); Record, Node::Record {fields, ..} => {
+            let node = match self.write_tag(fields, variant).with_path(name, variant)? {
+                Tagged::Content(content) => content.node,
+                Tagged::Fields(fields) => {
+                    return Ok(self.record(name, fields, None).with_path(name, variant));
+                }
+            };
+            let serializer = self.with_node(node);
+            match_schema!(serializer, ; (); Record, Node::Record {fields, plans, ..} => {
+                Ok(serializer.record(name, fields, Some(*plans)).with_path(name, variant))
             })
         });
     }
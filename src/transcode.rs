@@ -0,0 +1,276 @@
+//! Transcoding between the Avro binary and JSON encodings, driven by the schema only.
+
+use std::io;
+
+use integer_encoding::VarIntWriter;
+use serde::{
+    ser::{Error as _, SerializeMap, SerializeSeq},
+    Serialize,
+};
+use serde_json::Value;
+
+use crate::{
+    error::SerializationError,
+    json::{branch_name, decode_bytes, latin1},
+    node::{Node, NodeId},
+    read::Reader,
+};
+
+/// Binary encoded datum, serialized in the Avro JSON encoding as it is read.
+pub(crate) struct BinaryDatum<'a, 'de> {
+    pub(crate) schema: &'a Node,
+    pub(crate) nodes: &'a [Node],
+    pub(crate) reader: &'a Reader<'de>,
+}
+
+impl BinaryDatum<'_, '_> {
+    fn with_node(&self, id: NodeId) -> Self {
+        Self {
+            schema: &self.nodes[id as usize],
+            nodes: self.nodes,
+            reader: self.reader,
+        }
+    }
+}
+
+impl Serialize for BinaryDatum<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let reader = self.reader;
+        match self.schema {
+            Node::Null => serializer.serialize_unit(),
+            Node::Boolean => {
+                serializer.serialize_bool(reader.read_bool().map_err(S::Error::custom)?)
+            }
+            Node::Int | Node::Date | Node::TimeMillis => {
+                serializer.serialize_i32(reader.read_int().map_err(S::Error::custom)?)
+            }
+            Node::Long | Node::TimeMicros | Node::TimestampMillis | Node::TimestampMicros => {
+                serializer.serialize_i64(reader.read_long().map_err(S::Error::custom)?)
+            }
+            Node::Float => serializer.serialize_f32(reader.read_float().map_err(S::Error::custom)?),
+            Node::Double => {
+                serializer.serialize_f64(reader.read_double().map_err(S::Error::custom)?)
+            }
+            Node::Bytes => {
+                let bytes = reader.read_bytes().map_err(S::Error::custom)?;
+                serializer.serialize_str(&latin1(bytes))
+            }
+            Node::String | Node::Uuid => {
+                serializer.serialize_str(reader.read_str().map_err(S::Error::custom)?)
+            }
+            Node::Fixed { size, .. } => {
+                let bytes = reader.read_fixed(*size).map_err(S::Error::custom)?;
+                serializer.serialize_str(&latin1(bytes))
+            }
+            Node::Duration { .. } => {
+                let bytes = reader.read_fixed(12).map_err(S::Error::custom)?;
+                serializer.serialize_str(&latin1(bytes))
+            }
+            Node::Decimal { inner, .. } => self.with_node(*inner).serialize(serializer),
+            Node::Enum { symbols, .. } => {
+                let index = reader.read_index(symbols.len()).map_err(S::Error::custom)?;
+                serializer.serialize_str(symbols.get(index).unwrap())
+            }
+            Node::Array(items) => {
+                let mut seq = serializer.serialize_seq(None)?;
+                loop {
+                    match reader.read_block_len().map_err(S::Error::custom)? {
+                        0 => break,
+                        len => {
+                            for _ in 0..len {
+                                seq.serialize_element(&self.with_node(*items))?;
+                            }
+                        }
+                    }
+                }
+                seq.end()
+            }
+            Node::Map(values) => {
+                let mut map = serializer.serialize_map(None)?;
+                loop {
+                    match reader.read_block_len().map_err(S::Error::custom)? {
+                        0 => break,
+                        len => {
+                            for _ in 0..len {
+                                let key = reader.read_str().map_err(S::Error::custom)?;
+                                map.serialize_entry(key, &self.with_node(*values))?;
+                            }
+                        }
+                    }
+                }
+                map.end()
+            }
+            Node::Record { fields, .. } => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for field in fields {
+                    map.serialize_entry(&field.name, &self.with_node(field.node))?;
+                }
+                map.end()
+            }
+            Node::Union { branches, .. } | Node::Nullable { branches, .. } => {
+                let index = reader
+                    .read_index(branches.len())
+                    .map_err(S::Error::custom)?;
+                let branch = self.with_node(branches[index]);
+                if let Node::Null = branch.schema {
+                    return serializer.serialize_unit();
+                }
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&branch_name(branch.schema, self.nodes), &branch)?;
+                map.end()
+            }
+        }
+    }
+}
+
+/// Writes the binary encoding of a value in the Avro JSON encoding.
+pub(crate) struct JsonEncoder<'a, W> {
+    pub(crate) writer: W,
+    pub(crate) nodes: &'a [Node],
+}
+
+impl<'a, W> JsonEncoder<'a, W>
+where
+    W: io::Write,
+{
+    fn node(&self, id: NodeId) -> &'a Node {
+        &self.nodes[id as usize]
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SerializationError> {
+        self.writer.write_varint(bytes.len() as i64)?;
+        self.writer.write_all(bytes)?;
+        Ok(())
+    }
+
+    /// Field defaults are encoded like JSON values, except that unions take the first branch
+    /// without being wrapped in an object.
+    pub(crate) fn encode(
+        &mut self,
+        value: &Value,
+        schema: &Node,
+        default: bool,
+    ) -> Result<(), SerializationError> {
+        let mismatch = || {
+            format!(
+                "expected {}, found {value}",
+                branch_name(schema, self.nodes)
+            )
+        };
+        match schema {
+            Node::Null if value.is_null() => {}
+            Node::Boolean => {
+                let b = value.as_bool().ok_or_else(mismatch)?;
+                self.writer.write_all(&[u8::from(b)])?;
+            }
+            Node::Int | Node::Date | Node::TimeMillis => {
+                let n = value.as_i64().ok_or_else(mismatch)?;
+                let n = i32::try_from(n).map_err(|_| mismatch())?;
+                self.writer.write_varint(n)?;
+            }
+            Node::Long | Node::TimeMicros | Node::TimestampMillis | Node::TimestampMicros => {
+                self.writer
+                    .write_varint(value.as_i64().ok_or_else(mismatch)?)?;
+            }
+            Node::Float => {
+                let n = value.as_f64().ok_or_else(mismatch)? as f32;
+                self.writer.write_all(&n.to_le_bytes())?;
+            }
+            Node::Double => {
+                let n = value.as_f64().ok_or_else(mismatch)?;
+                self.writer.write_all(&n.to_le_bytes())?;
+            }
+            Node::Bytes => self.write_bytes(&decode_bytes(value)?)?,
+            Node::String | Node::Uuid => {
+                self.write_bytes(value.as_str().ok_or_else(mismatch)?.as_bytes())?
+            }
+            Node::Fixed { size, .. } => {
+                let bytes = decode_bytes(value)?;
+                if bytes.len() != *size {
+                    return Err(format!("expected fixed {size}, found {}", bytes.len()).into());
+                }
+                self.writer.write_all(&bytes)?;
+            }
+            Node::Duration { .. } => {
+                let bytes = decode_bytes(value)?;
+                if bytes.len() != 12 {
+                    return Err(
+                        format!("expected duration of 12 bytes, found {}", bytes.len()).into(),
+                    );
+                }
+                self.writer.write_all(&bytes)?;
+            }
+            Node::Decimal { inner, .. } => self.encode(value, self.node(*inner), default)?,
+            Node::Enum { symbols, .. } => {
+                let symbol = value.as_str().ok_or_else(mismatch)?;
+                let index = symbols
+                    .position(symbol)
+                    .ok_or_else(|| format!("unexpected {symbol} in enum"))?;
+                self.writer.write_varint(index as i64)?;
+            }
+            Node::Array(items) => {
+                let values = value.as_array().ok_or_else(mismatch)?;
+                if !values.is_empty() {
+                    self.writer.write_varint(values.len() as i64)?;
+                    for value in values {
+                        self.encode(value, self.node(*items), default)?;
+                    }
+                }
+                self.writer.write_all(&[0])?;
+            }
+            Node::Map(values) => {
+                let entries = value.as_object().ok_or_else(mismatch)?;
+                if !entries.is_empty() {
+                    self.writer.write_varint(entries.len() as i64)?;
+                    for (key, value) in entries {
+                        self.write_bytes(key.as_bytes())?;
+                        self.encode(value, self.node(*values), default)?;
+                    }
+                }
+                self.writer.write_all(&[0])?;
+            }
+            Node::Record { fields, .. } => {
+                let object = value.as_object().ok_or_else(mismatch)?;
+                for field in fields {
+                    match (object.get(&field.name), &field.default) {
+                        (Some(value), _) => self.encode(value, self.node(field.node), default)?,
+                        (None, Some(value)) => self.encode(value, self.node(field.node), true)?,
+                        (None, None) => {
+                            return Err(format!("missing field {}", field.name).into());
+                        }
+                    }
+                }
+            }
+            Node::Union { branches, .. } | Node::Nullable { branches, .. } => {
+                if default {
+                    self.writer.write_varint(0i64)?;
+                    return self.encode(value, self.node(branches[0]), default);
+                }
+                let mut nodes = branches.iter().map(|id| self.node(*id));
+                let (index, value) = match value {
+                    Value::Null => (
+                        nodes
+                            .position(|s| matches!(s, Node::Null))
+                            .ok_or_else(mismatch)?,
+                        value,
+                    ),
+                    Value::Object(object) if object.len() == 1 => {
+                        let (name, value) = object.iter().next().unwrap();
+                        let index = nodes
+                            .position(|s| branch_name(s, self.nodes) == *name || s.is_named(name))
+                            .ok_or_else(|| format!("unknown union branch {name}"))?;
+                        (index, value)
+                    }
+                    _ => return Err(mismatch().into()),
+                };
+                self.writer.write_varint(index as i64)?;
+                self.encode(value, self.node(branches[index]), default)?;
+            }
+            _ => return Err(mismatch().into()),
+        }
+        Ok(())
+    }
+}
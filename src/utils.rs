@@ -1,3 +1,5 @@
+use std::io::{self, IoSlice};
+
 use serde::ser::{Impossible, Serialize};
 
 use crate::error::SerializationError;
@@ -79,10 +81,7 @@ impl serde::Serializer for StringChecker {
         not_a_string()
     }
 
-    fn serialize_some<T: ?Sized>(self, _: &T) -> Result<Self::Ok, Self::Error>
-    where
-        T: Serialize,
-    {
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<Self::Ok, Self::Error> {
         not_a_string()
     }
 
@@ -103,27 +102,21 @@ impl serde::Serializer for StringChecker {
         not_a_string()
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
         self,
         _: &'static str,
         value: &T,
-    ) -> Result<Self::Ok, Self::Error>
-    where
-        T: Serialize,
-    {
+    ) -> Result<Self::Ok, Self::Error> {
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
         self,
         _: &'static str,
         _: u32,
         _: &'static str,
         _: &T,
-    ) -> Result<Self::Ok, Self::Error>
-    where
-        T: Serialize,
-    {
+    ) -> Result<Self::Ok, Self::Error> {
         not_a_string()
     }
 
@@ -175,3 +168,291 @@ impl serde::Serializer for StringChecker {
         not_a_string()
     }
 }
+
+/// Map key as displayed in error paths.
+pub(crate) fn key_string<K: ?Sized + Serialize>(key: &K) -> String {
+    match serde_json::to_value(key) {
+        Ok(serde_json::Value::String(key)) => key,
+        Ok(key) => key.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Checks that a map key is the expected record field name.
+pub struct FieldChecker<'a>(pub &'a str);
+
+fn not_a_field<Ok>() -> Result<Ok, SerializationError> {
+    Err("record key is not a string".into())
+}
+
+impl serde::Serializer for FieldChecker<'_> {
+    type Ok = ();
+    type Error = SerializationError;
+    type SerializeSeq = Impossible<(), SerializationError>;
+    type SerializeTuple = Impossible<(), SerializationError>;
+    type SerializeTupleStruct = Impossible<(), SerializationError>;
+    type SerializeTupleVariant = Impossible<(), SerializationError>;
+    type SerializeMap = Impossible<(), SerializationError>;
+    type SerializeStruct = Impossible<(), SerializationError>;
+    type SerializeStructVariant = Impossible<(), SerializationError>;
+
+    fn serialize_bool(self, _: bool) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_i8(self, _: i8) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_i16(self, _: i16) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_i32(self, _: i32) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_u8(self, _: u8) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_u16(self, _: u16) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_u32(self, _: u32) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_char(self, _: char) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        if v != self.0 {
+            return Err(format!("expected field {}, found {v}", self.0).into());
+        }
+        Ok(())
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        not_a_field()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        not_a_field()
+    }
+}
+
+/// Writer counting the bytes written to it, without storing them.
+#[derive(Default)]
+pub(crate) struct ByteCounter(pub(crate) usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0 += buf.len();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Capacity of the [`BufferedWriter`] buffer; larger writes bypass it.
+const BUFFER_CAPACITY: usize = 8 * 1024;
+
+/// Writer batching the small writes of the serialization, e.g. varints, so that writers like
+/// files or sockets are not called for every value. Larger payloads are written along with the
+/// buffered bytes in a single vectored write.
+pub(crate) struct BufferedWriter<W> {
+    writer: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: io::Write> BufferedWriter<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Write the buffered bytes, which are otherwise lost when the writer is dropped.
+    pub(crate) fn flush_buffer(&mut self) -> io::Result<()> {
+        self.writer.write_all(&self.buffer)?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    #[cold]
+    fn write_all_cold(&mut self, buf: &[u8]) -> io::Result<()> {
+        if buf.len() < BUFFER_CAPACITY {
+            if self.buffer.len() + buf.len() > BUFFER_CAPACITY {
+                self.flush_buffer()?;
+            }
+            // Small values don't need the whole capacity.
+            let capacity = (self.buffer.len() + buf.len()).max(2 * self.buffer.capacity());
+            self.buffer
+                .reserve_exact(capacity.clamp(256, BUFFER_CAPACITY) - self.buffer.len());
+            self.buffer.extend_from_slice(buf);
+            return Ok(());
+        }
+        let (mut buffered, mut buf) = (&self.buffer[..], buf);
+        while !buffered.is_empty() || !buf.is_empty() {
+            let written = if buffered.is_empty() {
+                self.writer.write(buf)
+            } else {
+                self.writer
+                    .write_vectored(&[IoSlice::new(buffered), IoSlice::new(buf)])
+            };
+            match written {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    ))
+                }
+                Ok(mut n) => {
+                    let from_buffer = n.min(buffered.len());
+                    buffered = &buffered[from_buffer..];
+                    n -= from_buffer;
+                    buf = &buf[n..];
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<W: io::Write> io::Write for BufferedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        if buf.len() <= self.buffer.capacity() - self.buffer.len() {
+            self.buffer.extend_from_slice(buf);
+            return Ok(());
+        }
+        self.write_all_cold(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buffer()?;
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,171 @@
+use std::borrow::Cow;
+
+use serde::{
+    ser::{SerializeMap, SerializeSeq},
+    Serialize,
+};
+
+use crate::{
+    error::SerializationError,
+    node::{Node, NodeId},
+    read::Reader,
+};
+
+/// Name of the newtype variant carrying the branch index of [`Value::Union`] to the serializer.
+pub(crate) const UNION_BRANCH: &str = "$avro_poc::private::UnionBranch";
+
+/// Dynamically typed Avro value, for schemas not known at compile time.
+///
+/// Decoded values borrow strings and bytes from the input, and record field names from the
+/// schema. Values are serialized with [`Serializer`](crate::Serializer) like any other type,
+/// [`Value::Union`] keeping its branch index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    Null,
+    Boolean(bool),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Bytes(Cow<'a, [u8]>),
+    String(Cow<'a, str>),
+    Array(Vec<Value<'a>>),
+    /// Entries in encoding order.
+    Map(Vec<(Cow<'a, str>, Value<'a>)>),
+    Union(usize, Box<Value<'a>>),
+    /// Fields in schema order.
+    Record(Vec<(Cow<'a, str>, Value<'a>)>),
+    Enum(Cow<'a, str>),
+    Fixed(Cow<'a, [u8]>),
+    /// Big-endian two's complement bytes of the unscaled value.
+    Decimal(Cow<'a, [u8]>),
+    Uuid(Cow<'a, str>),
+    Date(i32),
+    TimeMillis(i32),
+    TimeMicros(i64),
+    TimestampMillis(i64),
+    TimestampMicros(i64),
+    Duration(Cow<'a, [u8]>),
+}
+
+impl Serialize for Value<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::Int(n) | Value::Date(n) | Value::TimeMillis(n) => serializer.serialize_i32(*n),
+            Value::Long(n)
+            | Value::TimeMicros(n)
+            | Value::TimestampMillis(n)
+            | Value::TimestampMicros(n) => serializer.serialize_i64(*n),
+            Value::Float(n) => serializer.serialize_f32(*n),
+            Value::Double(n) => serializer.serialize_f64(*n),
+            Value::Bytes(bytes)
+            | Value::Fixed(bytes)
+            | Value::Decimal(bytes)
+            | Value::Duration(bytes) => serializer.serialize_bytes(bytes),
+            Value::String(s) | Value::Enum(s) | Value::Uuid(s) => serializer.serialize_str(s),
+            Value::Array(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Value::Map(entries) | Value::Record(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            Value::Union(index, value) => {
+                serializer.serialize_newtype_variant(UNION_BRANCH, *index as u32, "", value)
+            }
+        }
+    }
+}
+
+/// Decodes binary encoded values.
+pub(crate) struct Decoder<'a> {
+    pub(crate) reader: Reader<'a>,
+    pub(crate) nodes: &'a [Node],
+}
+
+impl<'a> Decoder<'a> {
+    fn node(&self, id: NodeId) -> &'a Node {
+        &self.nodes[id as usize]
+    }
+
+    fn entries<T>(
+        &self,
+        mut read: impl FnMut() -> Result<T, SerializationError>,
+    ) -> Result<Vec<T>, SerializationError> {
+        let mut entries = Vec::new();
+        loop {
+            match self.reader.read_block_len()? {
+                0 => return Ok(entries),
+                len => {
+                    // The length comes from the input, so don't trust it for large allocations.
+                    entries.reserve(len.min(1024));
+                    for _ in 0..len {
+                        entries.push(read()?);
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn decode(&self, schema: &'a Node) -> Result<Value<'a>, SerializationError> {
+        let reader = &self.reader;
+        Ok(match schema {
+            Node::Null => Value::Null,
+            Node::Boolean => Value::Boolean(reader.read_bool()?),
+            Node::Int => Value::Int(reader.read_int()?),
+            Node::Long => Value::Long(reader.read_long()?),
+            Node::Float => Value::Float(reader.read_float()?),
+            Node::Double => Value::Double(reader.read_double()?),
+            Node::Bytes => Value::Bytes(reader.read_bytes()?.into()),
+            Node::String => Value::String(reader.read_str()?.into()),
+            Node::Array(items) => Value::Array(self.entries(|| self.decode(self.node(*items)))?),
+            Node::Map(values) => {
+                let values = self.node(*values);
+                Value::Map(self.entries(|| Ok((reader.read_str()?.into(), self.decode(values)?)))?)
+            }
+            Node::Union { branches, .. } | Node::Nullable { branches, .. } => {
+                let index = reader.read_index(branches.len())?;
+                Value::Union(index, Box::new(self.decode(self.node(branches[index]))?))
+            }
+            Node::Record { fields, .. } => Value::Record(
+                fields
+                    .iter()
+                    .map(|field| {
+                        Ok((
+                            field.name.as_str().into(),
+                            self.decode(self.node(field.node))?,
+                        ))
+                    })
+                    .collect::<Result<_, SerializationError>>()?,
+            ),
+            Node::Enum { symbols, .. } => {
+                let index = reader.read_index(symbols.len())?;
+                Value::Enum(symbols.get(index).unwrap().into())
+            }
+            Node::Fixed { size, .. } => Value::Fixed(reader.read_fixed(*size)?.into()),
+            Node::Decimal { inner, .. } => match self.decode(self.node(*inner))? {
+                Value::Bytes(bytes) | Value::Fixed(bytes) => Value::Decimal(bytes),
+                _ => unreachable!(),
+            },
+            Node::Uuid => Value::Uuid(reader.read_str()?.into()),
+            Node::Date => Value::Date(reader.read_int()?),
+            Node::TimeMillis => Value::TimeMillis(reader.read_int()?),
+            Node::TimeMicros => Value::TimeMicros(reader.read_long()?),
+            Node::TimestampMillis => Value::TimestampMillis(reader.read_long()?),
+            Node::TimestampMicros => Value::TimestampMicros(reader.read_long()?),
+            Node::Duration { .. } => Value::Duration(reader.read_fixed(12)?.into()),
+        })
+    }
+}
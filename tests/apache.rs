@@ -0,0 +1,154 @@
+#![cfg(feature = "apache-avro")]
+
+use std::collections::{BTreeMap, HashMap};
+
+use apache_avro::{
+    schema::{Name, RecordField, RecordFieldOrder},
+    types::Value,
+    Schema,
+};
+use avro_poc::{PathSegment, SerializationError, Serializer};
+
+fn path(result: Result<Vec<u8>, SerializationError>) -> Vec<PathSegment> {
+    match result {
+        Err(SerializationError::SchemaMismatch { path, .. }) => path.into(),
+        result => panic!("expected a schema mismatch, found {result:?}"),
+    }
+}
+
+#[test]
+fn mismatches_report_their_path() {
+    let serializer = Serializer::parse_str(
+        r#"{"type": "record", "name": "Batch", "fields": [
+            {"name": "items", "type": {"type": "array", "items": {"type": "map", "values": "int"}}}
+        ]}"#,
+    )
+    .unwrap();
+    let batch = Value::Record(vec![(
+        "items".into(),
+        Value::Array(vec![
+            Value::Map(HashMap::new()),
+            Value::Map(HashMap::from([("k".into(), Value::String("x".into()))])),
+        ]),
+    )]);
+    assert_eq!(
+        path(serializer.serialize_apache_value(&batch)),
+        [
+            PathSegment::Key("items".into()),
+            PathSegment::Index(1),
+            PathSegment::Key("k".into()),
+        ]
+    );
+}
+
+#[test]
+fn unions_use_the_branch_index_or_the_matching_branch() {
+    let serializer = Serializer::parse_str(r#"["null", "string", "int"]"#).unwrap();
+    assert_eq!(
+        serializer
+            .serialize_apache_value(&Value::Union(2, Box::new(Value::Int(3))))
+            .unwrap(),
+        [4, 6]
+    );
+    assert_eq!(
+        serializer.serialize_apache_value(&Value::Int(3)).unwrap(),
+        [4, 6]
+    );
+    assert_eq!(
+        serializer.serialize_apache_value(&Value::Null).unwrap(),
+        [0]
+    );
+    assert_eq!(
+        path(serializer.serialize_apache_value(&Value::Union(1, Box::new(Value::Int(3))))),
+        [PathSegment::Branch(1)]
+    );
+    assert!(serializer
+        .serialize_apache_value(&Value::Union(3, Box::new(Value::Null)))
+        .is_err());
+    assert!(serializer
+        .serialize_apache_value(&Value::Boolean(true))
+        .is_err());
+}
+
+#[test]
+fn record_fields_are_matched_by_name() {
+    let serializer = Serializer::parse_str(
+        r#"{"type": "record", "name": "Point", "fields": [
+            {"name": "x", "type": "int"},
+            {"name": "y", "type": "int"},
+            {"name": "label", "type": "string", "default": "p"}
+        ]}"#,
+    )
+    .unwrap();
+    let point = Value::Record(vec![
+        ("y".into(), Value::Int(2)),
+        ("x".into(), Value::Int(1)),
+    ]);
+    assert_eq!(
+        serializer.serialize_apache_value(&point).unwrap(),
+        [2, 4, 2, b'p']
+    );
+    let point = Value::Record(vec![("x".into(), Value::Int(1))]);
+    assert!(serializer.serialize_apache_value(&point).is_err());
+}
+
+#[test]
+fn field_aliases_and_enum_defaults_are_kept() {
+    let name = |name: &str| Name {
+        name: name.into(),
+        namespace: None,
+    };
+    let color = Schema::Enum {
+        name: name("Color"),
+        aliases: None,
+        doc: None,
+        symbols: vec!["Red".into(), "Blue".into()],
+        default: Some("Red".into()),
+        attributes: BTreeMap::new(),
+    };
+    let paint = Schema::Record {
+        name: name("Paint"),
+        aliases: None,
+        doc: None,
+        fields: vec![RecordField {
+            name: "color".into(),
+            doc: None,
+            aliases: Some(vec!["colour".into()]),
+            default: None,
+            schema: color,
+            order: RecordFieldOrder::Ascending,
+            position: 0,
+            custom_attributes: BTreeMap::new(),
+        }],
+        lookup: BTreeMap::from([("color".into(), 0)]),
+        attributes: BTreeMap::new(),
+    };
+    let parsed = Serializer::parse_str(
+        r#"{"type": "record", "name": "Paint", "fields": [{
+            "name": "color",
+            "aliases": ["colour"],
+            "type": {"type": "enum", "name": "Color", "symbols": ["Red", "Blue"], "default": "Red"}
+        }]}"#,
+    )
+    .unwrap();
+    assert_eq!(Serializer::new(&paint).unwrap().schema(), parsed.schema());
+}
+
+#[test]
+fn enums_built_with_duplicate_symbols_are_errors() {
+    let suit = Schema::Enum {
+        name: Name {
+            name: "Suit".into(),
+            namespace: None,
+        },
+        aliases: None,
+        doc: None,
+        symbols: vec!["Spades".into(), "Hearts".into(), "Spades".into()],
+        default: None,
+        attributes: BTreeMap::new(),
+    };
+    assert!(matches!(
+        Serializer::new(&suit),
+        Err(apache_avro::Error::EnumSymbolDuplicate(symbol)) if symbol == "Spades"
+    ));
+}
@@ -0,0 +1,102 @@
+use avro_poc::{codegen, Serializer, SerializerConfig};
+
+mod generated {
+    include!("fixtures/codegen/order.rs");
+}
+
+use generated::*;
+
+/// The root schema first, as for `generate_files`.
+const FILES: [&str; 2] = [
+    "tests/fixtures/codegen/order.avsc",
+    "tests/fixtures/codegen/address.avsc",
+];
+
+fn serializer() -> Serializer {
+    let dependencies = [std::fs::read_to_string(FILES[1]).unwrap()];
+    Serializer::parse_with_dependencies(
+        &std::fs::read_to_string(FILES[0]).unwrap(),
+        &[dependencies[0].as_str()],
+        SerializerConfig::default(),
+    )
+    .unwrap()
+}
+
+fn order(payment: OrderPayment2, refund: Option<OrderRefund>) -> Order {
+    Order {
+        id: Id(vec![1, 2, 3, 4]),
+        note: None,
+        shipping: None,
+        payment,
+        refund,
+        state: State::Shipped,
+        line_count: 1,
+        line_count2: 2,
+        options: OrderPayment { r#type: true },
+    }
+}
+
+#[test]
+fn generated_code_is_up_to_date() {
+    assert_eq!(
+        codegen::generate_files(&FILES).unwrap(),
+        include_str!("fixtures/codegen/order.rs")
+    );
+}
+
+#[test]
+fn generated_types_round_trip() {
+    let serializer = serializer();
+    let card = Card {
+        number: "4242".into(),
+    };
+    let orders = [
+        order(OrderPayment2::Card(card.clone()), None),
+        order(
+            OrderPayment2::Voucher(Voucher { code: "A1".into() }),
+            Some(OrderRefund::Card(card)),
+        ),
+        Order {
+            note: Some("gift".into()),
+            shipping: Some(Address {
+                street: "Main St".into(),
+                zip: None,
+            }),
+            state: State::InStock2,
+            ..order(
+                OrderPayment2::Long(12),
+                Some(OrderRefund::String("cash".into())),
+            )
+        },
+    ];
+    for order in orders {
+        let bytes = serializer.serialize(&order).unwrap();
+        let mut json = Vec::new();
+        serializer
+            .binary_to_json(&bytes, &mut serde_json::Serializer::new(&mut json))
+            .unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&serializer.serialize_json(&order).unwrap())
+                .unwrap()
+        );
+        assert_eq!(serializer.deserialize_json::<Order>(&json).unwrap(), order);
+    }
+}
+
+#[test]
+fn clashing_names_keep_their_avro_names() {
+    let serializer = serializer();
+    let order = Order {
+        state: State::InStock2,
+        ..order(OrderPayment2::Long(1), None)
+    };
+    let json: serde_json::Value =
+        serde_json::from_str(&serializer.serialize_json(&order).unwrap()).unwrap();
+    assert_eq!(json["state"], "IN_STOCK");
+    assert_eq!(json["lineCount"], 1);
+    assert_eq!(json["line_count"], 2);
+    assert_eq!(json["payment"], serde_json::json!({"long": 1}));
+    assert_eq!(json["options"], serde_json::json!({"type": true}));
+}
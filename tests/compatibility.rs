@@ -0,0 +1,197 @@
+use avro_poc::{
+    compatibility::{
+        check, check_history, CompatibilityLevel, Incompatibility, IncompatibilityKind,
+    },
+    SerializationSchemaKind, Serializer, SerializerConfig,
+};
+
+const OUTER: &str = r#"{"type": "record", "name": "Outer", "fields": [
+    {"name": "a", "type": ["null", "Inner"]},
+    {"name": "b", "type": "Inner"}
+]}"#;
+
+#[test]
+fn failed_union_probes_do_not_skip_later_checks() {
+    let parse = |inner| {
+        Serializer::parse_with_dependencies(OUTER, &[inner], SerializerConfig::default()).unwrap()
+    };
+    let reader = parse(
+        r#"{"type": "record", "name": "Inner", "fields": [
+            {"name": "x", "type": "int"},
+            {"name": "y", "type": "string"}
+        ]}"#,
+    );
+    let writer =
+        parse(r#"{"type": "record", "name": "Inner", "fields": [{"name": "x", "type": "int"}]}"#);
+    let incompatibilities = check(&reader, &writer)
+        .into_iter()
+        .map(|incompatibility| (incompatibility.path, incompatibility.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        incompatibilities,
+        [
+            (
+                vec!["Outer".to_string(), "a".to_string()],
+                IncompatibilityKind::MissingUnionBranch(SerializationSchemaKind::Record)
+            ),
+            (
+                ["Outer", "b", "Inner", "y"].map(String::from).to_vec(),
+                IncompatibilityKind::MissingDefault("y".into())
+            ),
+        ]
+    );
+}
+
+fn record(fields: &str) -> Serializer {
+    Serializer::parse_str(&format!(
+        r#"{{"type": "record", "name": "Order", "fields": [{fields}]}}"#
+    ))
+    .unwrap()
+}
+
+fn kinds(incompatibilities: Vec<Incompatibility>) -> Vec<IncompatibilityKind> {
+    incompatibilities.into_iter().map(|i| i.kind).collect()
+}
+
+#[test]
+fn levels_check_the_latest_or_every_version_in_either_direction() {
+    let history = [
+        record(r#"{"name": "a", "type": "int"}"#),
+        record(r#"{"name": "a", "type": "int"}, {"name": "b", "type": "string", "default": ""}"#),
+    ];
+    let schema = record(r#"{"name": "a", "type": "long"}, {"name": "b", "type": "string"}"#);
+    let missing_default = IncompatibilityKind::MissingDefault("b".into());
+    let narrowing = IncompatibilityKind::TypeMismatch {
+        reader: SerializationSchemaKind::Int,
+        writer: SerializationSchemaKind::Long,
+    };
+    let check = |level| {
+        check_history(&schema, &history, level)
+            .into_iter()
+            .map(|i| (i.version, i.backward, i.incompatibility.kind))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(check(CompatibilityLevel::Backward), []);
+    assert_eq!(
+        check(CompatibilityLevel::BackwardTransitive),
+        [(0, true, missing_default.clone())]
+    );
+    assert_eq!(
+        check(CompatibilityLevel::Forward),
+        [(1, false, narrowing.clone())]
+    );
+    assert_eq!(
+        check(CompatibilityLevel::ForwardTransitive),
+        [(0, false, narrowing.clone()), (1, false, narrowing.clone())]
+    );
+    assert_eq!(
+        check(CompatibilityLevel::Full),
+        [(1, false, narrowing.clone())]
+    );
+    assert_eq!(
+        check(CompatibilityLevel::FullTransitive),
+        [
+            (0, true, missing_default),
+            (0, false, narrowing.clone()),
+            (1, false, narrowing)
+        ]
+    );
+}
+
+#[test]
+fn reader_fields_missing_in_the_writer_need_a_default() {
+    let writer = record(r#"{"name": "a", "type": "int"}"#);
+    let reader =
+        record(r#"{"name": "a", "type": "int"}, {"name": "b", "type": "int", "default": 0}"#);
+    assert_eq!(kinds(check(&reader, &writer)), []);
+    // Writer fields missing in the reader are skipped.
+    assert_eq!(kinds(check(&writer, &reader)), []);
+    let reader = record(r#"{"name": "a", "type": "int"}, {"name": "b", "type": "int"}"#);
+    assert_eq!(
+        kinds(check(&reader, &writer)),
+        [IncompatibilityKind::MissingDefault("b".into())]
+    );
+}
+
+#[test]
+fn reader_aliases_match_writer_names() {
+    let writer = Serializer::parse_str(
+        r#"{"type": "record", "name": "Old", "namespace": "shop", "fields": [
+            {"name": "sum", "type": "int"}
+        ]}"#,
+    )
+    .unwrap();
+    // Aliases are qualified by the namespace of the reader type.
+    let reader = Serializer::parse_str(
+        r#"{"type": "record", "name": "New", "namespace": "shop", "aliases": ["Old"], "fields": [
+            {"name": "total", "type": "int", "aliases": ["sum"]}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(kinds(check(&reader, &writer)), []);
+    let reader = Serializer::parse_str(
+        r#"{"type": "record", "name": "New", "namespace": "store", "aliases": ["Old"], "fields": [
+            {"name": "total", "type": "int", "aliases": ["sum"]}
+        ]}"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        kinds(check(&reader, &writer))[..],
+        [IncompatibilityKind::NameMismatch { .. }]
+    ));
+    // Names are compared unqualified.
+    let reader = Serializer::parse_str(
+        r#"{"type": "record", "name": "Old", "namespace": "store", "fields": [
+            {"name": "sum", "type": "int"}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(kinds(check(&reader, &writer)), []);
+}
+
+#[test]
+fn enum_symbols_missing_in_the_reader_need_a_default() {
+    let suit = |symbols: &str, default: &str| {
+        Serializer::parse_str(&format!(
+            r#"{{"type": "enum", "name": "Suit", "symbols": [{symbols}]{default}}}"#
+        ))
+        .unwrap()
+    };
+    let writer = suit(r#""SPADES", "HEARTS", "CLUBS""#, "");
+    assert_eq!(
+        kinds(check(&suit(r#""SPADES", "HEARTS""#, ""), &writer)),
+        [IncompatibilityKind::MissingEnumSymbols(
+            vec!["CLUBS".into()]
+        )]
+    );
+    let reader = suit(r#""SPADES", "HEARTS""#, r#", "default": "SPADES""#);
+    assert_eq!(kinds(check(&reader, &writer)), []);
+}
+
+#[test]
+fn writer_types_are_promoted_to_wider_reader_types() {
+    let parse = |schema: &str| Serializer::parse_str(&format!(r#""{schema}""#)).unwrap();
+    for (reader, writer) in [
+        ("long", "int"),
+        ("float", "int"),
+        ("float", "long"),
+        ("double", "int"),
+        ("double", "long"),
+        ("double", "float"),
+        ("bytes", "string"),
+        ("string", "bytes"),
+    ] {
+        assert_eq!(
+            kinds(check(&parse(reader), &parse(writer))),
+            [],
+            "{writer} as {reader}"
+        );
+    }
+    for (reader, writer) in [("int", "long"), ("float", "double"), ("int", "string")] {
+        assert_eq!(
+            check(&parse(reader), &parse(writer)).len(),
+            1,
+            "{writer} as {reader}"
+        );
+    }
+}
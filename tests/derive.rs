@@ -0,0 +1,308 @@
+#![cfg(feature = "derive")]
+
+use avro_poc::{AvroSchema, SchemaError, Serializer};
+
+/// The derived schema is the one parsed from `expected`.
+fn assert_schema<T: AvroSchema>(expected: &str) {
+    assert_eq!(
+        Serializer::for_type::<T>().unwrap().schema(),
+        Serializer::parse_str(expected).unwrap().schema()
+    );
+}
+
+#[derive(serde::Serialize, AvroSchema)]
+enum Shape {
+    Empty,
+    Circle { radius: i32 },
+    Side(Side),
+}
+
+#[derive(serde::Serialize, AvroSchema)]
+struct Side {
+    length: i32,
+}
+
+#[derive(serde::Serialize, AvroSchema)]
+struct Pair {
+    a: Shape,
+    b: Shape,
+}
+
+#[test]
+fn enums_used_twice_reuse_their_union() {
+    let serializer = Serializer::for_type::<Pair>().unwrap();
+    let pair = Pair {
+        a: Shape::Circle { radius: 1 },
+        b: Shape::Side(Side { length: 2 }),
+    };
+    assert_eq!(serializer.serialize(&pair).unwrap(), [2, 2, 4, 4]);
+    let pair = Pair {
+        a: Shape::Empty,
+        b: Shape::Circle { radius: 3 },
+    };
+    assert_eq!(serializer.serialize(&pair).unwrap(), [0, 0, 2, 6]);
+}
+
+#[allow(dead_code)]
+#[derive(AvroSchema)]
+enum Tree {
+    Leaf(i32),
+    Node { children: Vec<Tree> },
+}
+
+#[test]
+fn recursive_enums_are_schema_errors() {
+    assert!(matches!(
+        Serializer::for_type::<Tree>(),
+        Err(SchemaError::Invalid { error, .. }) if error.contains("recursive enum")
+    ));
+}
+
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+#[serde(rename = "Renamed")]
+struct Original {
+    #[serde(rename = "b")]
+    a: i32,
+    #[serde(rename(serialize = "d", deserialize = "x"))]
+    c: i32,
+}
+
+#[test]
+fn rename_sets_type_and_field_names() {
+    assert_schema::<Original>(
+        r#"{"type": "record", "name": "Renamed", "fields": [
+            {"name": "b", "type": "int"},
+            {"name": "d", "type": "int"}
+        ]}"#,
+    );
+}
+
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+#[serde(rename_all = "camelCase")]
+struct Person {
+    first_name: String,
+    #[serde(rename = "surname")]
+    last_name: String,
+}
+
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum Priority {
+    LowPriority,
+    High,
+}
+
+#[test]
+fn rename_all_applies_to_fields_and_variants() {
+    assert_schema::<Person>(
+        r#"{"type": "record", "name": "Person", "fields": [
+            {"name": "firstName", "type": "string"},
+            {"name": "surname", "type": "string"}
+        ]}"#,
+    );
+    assert_schema::<Priority>(
+        r#"{"type": "enum", "name": "Priority", "symbols": ["LOW_PRIORITY", "HIGH"]}"#,
+    );
+}
+
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+struct Cached {
+    key: String,
+    #[serde(skip)]
+    hits: u64,
+    #[serde(skip_serializing)]
+    internal: bool,
+}
+
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+enum State {
+    On,
+    #[serde(skip)]
+    Unknown,
+    Off,
+}
+
+#[test]
+fn skipped_fields_and_variants_are_left_out() {
+    assert_schema::<Cached>(
+        r#"{"type": "record", "name": "Cached", "fields": [{"name": "key", "type": "string"}]}"#,
+    );
+    assert_schema::<State>(r#"{"type": "enum", "name": "State", "symbols": ["On", "Off"]}"#);
+}
+
+fn ten() -> i64 {
+    10
+}
+
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+struct Query {
+    #[serde(default)]
+    offset: i32,
+    #[serde(default = "ten")]
+    limit: i64,
+    text: String,
+}
+
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+#[serde(default)]
+struct Settings {
+    retries: i32,
+    name: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            name: "main".into(),
+        }
+    }
+}
+
+#[test]
+fn defaults_come_from_the_field_or_the_container() {
+    assert_schema::<Query>(
+        r#"{"type": "record", "name": "Query", "fields": [
+            {"name": "offset", "type": "int", "default": 0},
+            {"name": "limit", "type": "long", "default": 10},
+            {"name": "text", "type": "string"}
+        ]}"#,
+    );
+    assert_schema::<Settings>(
+        r#"{"type": "record", "name": "Settings", "fields": [
+            {"name": "retries", "type": "int", "default": 3},
+            {"name": "name", "type": "string", "default": "main"}
+        ]}"#,
+    );
+}
+
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+#[avro(namespace = "geo")]
+enum Axis {
+    X,
+    Y,
+}
+
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+#[avro(namespace = "geo")]
+struct Vector {
+    axis: Axis,
+    length: f64,
+}
+
+#[test]
+fn namespace_qualifies_the_type_name() {
+    assert_schema::<Vector>(
+        r#"{"type": "record", "name": "Vector", "namespace": "geo", "fields": [
+            {"name": "axis", "type": {"type": "enum", "name": "Axis", "namespace": "geo", "symbols": ["X", "Y"]}},
+            {"name": "length", "type": "double"}
+        ]}"#,
+    );
+    assert_eq!(
+        Serializer::for_type::<Vector>().unwrap().canonical_form(),
+        r#"{"name":"geo.Vector","type":"record","fields":[{"name":"axis","type":{"name":"geo.Axis","type":"enum","symbols":["X","Y"]}},{"name":"length","type":"double"}]}"#
+    );
+}
+
+/// A point
+/// in the plane.
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+struct Documented {
+    /// Abscissa.
+    x: i32,
+}
+
+#[test]
+fn doc_comments_become_docs() {
+    assert_schema::<Documented>(
+        r#"{"type": "record", "name": "Documented", "doc": "A point\nin the plane.", "fields": [
+            {"name": "x", "type": "int", "doc": "Abscissa."}
+        ]}"#,
+    );
+}
+
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+struct Optional {
+    value: Option<String>,
+}
+
+#[test]
+fn options_are_nullable_unions() {
+    assert_schema::<Optional>(
+        r#"{"type": "record", "name": "Optional", "fields": [
+            {"name": "value", "type": ["null", "string"]}
+        ]}"#,
+    );
+}
+
+#[allow(dead_code)]
+#[derive(serde::Serialize, AvroSchema)]
+struct Wrapper<T> {
+    value: T,
+    values: Vec<T>,
+}
+
+#[test]
+fn generic_parameters_take_the_schema_of_their_type() {
+    assert_schema::<Wrapper<i64>>(
+        r#"{"type": "record", "name": "Wrapper", "fields": [
+            {"name": "value", "type": "long"},
+            {"name": "values", "type": {"type": "array", "items": "long"}}
+        ]}"#,
+    );
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize, AvroSchema)]
+#[serde(rename_all = "camelCase")]
+#[avro(namespace = "shop")]
+struct Order {
+    order_id: i64,
+    note: Option<String>,
+    #[serde(default)]
+    quantity: i32,
+    lines: Vec<Line>,
+    status: Status,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize, AvroSchema)]
+struct Line {
+    sku: String,
+    price: f64,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize, AvroSchema)]
+enum Status {
+    Open,
+    Shipped,
+}
+
+#[test]
+fn derived_schemas_round_trip_values() {
+    let serializer = Serializer::for_type::<Order>().unwrap();
+    let order = Order {
+        order_id: 7,
+        note: Some("fragile".into()),
+        quantity: 2,
+        lines: vec![Line {
+            sku: "a-1".into(),
+            price: 2.5,
+        }],
+        status: Status::Shipped,
+    };
+    let bytes = serializer.serialize(&order).unwrap();
+    let value = serializer.decode(&bytes).unwrap();
+    assert_eq!(serializer.serialize(&value).unwrap(), bytes);
+    let json = serializer.serialize_json(&order).unwrap();
+    assert_eq!(serializer.deserialize_json::<Order>(&json).unwrap(), order);
+}
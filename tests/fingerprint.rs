@@ -0,0 +1,92 @@
+use avro_poc::{Fingerprint, Serializer, SerializerConfig};
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Schema, canonical form, and its Rabin, MD5 and SHA-256 fingerprints. Rabin fingerprints are
+/// Java longs: those of the primitive and fixed schemas come from the `schema-tests.txt` of the
+/// reference implementation, the others from the algorithm given in the specification.
+const VECTORS: &[(&str, &str, i64, &str, &str)] = &[
+    (
+        r#""null""#,
+        r#""null""#,
+        7195948357588979594,
+        "9b41ef67651c18488a8b08bb67c75699",
+        "f072cbec3bf8841871d4284230c5e983dc211a56837aed862487148f947d1a1f",
+    ),
+    (
+        r#"{"type": "int"}"#,
+        r#""int""#,
+        8247732601305521295,
+        "ef524ea1b91e73173d938ade36c1db32",
+        "3f2b87a9fe7cc9b13835598c3981cd45e3e355309e5090aa0933d7becb6fba45",
+    ),
+    (
+        r#""string""#,
+        r#""string""#,
+        -8142146995180207161,
+        "095d71cf12556b9d5e330ad575b3df5d",
+        "e9e5c1c9e4f6277339d1bcde0733a59bd42f8731f449da6dc13010a916930d48",
+    ),
+    (
+        r#"{"type": "fixed", "name": "foo", "size": 15}"#,
+        r#"{"name":"foo","type":"fixed","size":15}"#,
+        1756455273707447556,
+        "b0cf9227ad58a83b195b5aeb4593140f",
+        "802428b30753d93ff41de7ee0e319755f8765c014184311efe67b06453b545e5",
+    ),
+    (
+        r#"{"type": "record", "name": "test", "namespace": "ns", "doc": "stripped", "fields": [
+            {"name": "a", "type": "long", "default": 42, "aliases": ["x"]},
+            {"name": "b", "type": {"type": "string", "logicalType": "uuid"}}
+        ]}"#,
+        r#"{"name":"ns.test","type":"record","fields":[{"name":"a","type":"long"},{"name":"b","type":"string"}]}"#,
+        6725345814053389428,
+        "aa16dfd218df0e409f227b384aa700f6",
+        "24544cb93f6a9387407d111a7bee66557928d5fb56dfd353a07e6d04dc511b26",
+    ),
+    (
+        r#"{"type": "enum", "name": "Suit", "symbols": ["SPADES", "HEARTS"], "default": "HEARTS"}"#,
+        r#"{"name":"Suit","type":"enum","symbols":["SPADES","HEARTS"]}"#,
+        6369477722858210447,
+        "03f9acddc3c04393e94d52afd95836b0",
+        "7348bedec95b831bf56171188c8cc0bee6f1dcc27f279f4772e398c86da4570f",
+    ),
+];
+
+#[test]
+fn fingerprints_match_the_reference_implementation() {
+    for &(schema, canonical_form, rabin, md5, sha256) in VECTORS {
+        let serializer = Serializer::parse_str(schema).unwrap();
+        assert_eq!(serializer.canonical_form(), canonical_form);
+        let fingerprint = serializer.fingerprint();
+        assert_eq!(fingerprint.rabin as i64, rabin, "{canonical_form}");
+        assert_eq!(hex(&fingerprint.md5), md5, "{canonical_form}");
+        assert_eq!(hex(&fingerprint.sha256), sha256, "{canonical_form}");
+        assert_eq!(Fingerprint::new(serializer.schema()), *fingerprint);
+    }
+}
+
+#[test]
+fn canonical_form_inlines_a_type_at_its_first_use() {
+    let serializer = Serializer::parse_with_dependencies(
+        r#"{"type": "record", "name": "Ledger", "fields": [
+            {"name": "entries", "type": {"type": "array", "items": "money.Amount"}},
+            {"name": "totals", "type": {"type": "map", "values": "money.Amount"}},
+            {"name": "last", "type": ["null", "money.Amount"]}
+        ]}"#,
+        &[r#"{"type": "fixed", "name": "Amount", "namespace": "money", "size": 8}"#],
+        SerializerConfig::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        serializer.canonical_form(),
+        r#"{"name":"Ledger","type":"record","fields":[{"name":"entries","type":{"type":"array","items":{"name":"money.Amount","type":"fixed","size":8}}},{"name":"totals","type":{"type":"map","values":"money.Amount"}},{"name":"last","type":["null","money.Amount"]}]}"#
+    );
+    // Without its dependencies, the schema only names the referenced type.
+    assert_eq!(
+        serializer.schema().canonical_form(),
+        r#"{"name":"Ledger","type":"record","fields":[{"name":"entries","type":{"type":"array","items":"money.Amount"}},{"name":"totals","type":{"type":"map","values":"money.Amount"}},{"name":"last","type":["null","money.Amount"]}]}"#
+    );
+}
@@ -0,0 +1,66 @@
+// Generated by avro_poc::codegen, do not edit.
+
+/// An order of the shop.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Order {
+    pub id: Id,
+    pub note: Option<String>,
+    pub shipping: Option<Address>,
+    /// How the order is paid.
+    pub payment: OrderPayment2,
+    pub refund: Option<OrderRefund>,
+    pub state: State,
+    #[serde(rename = "lineCount")]
+    pub line_count: i32,
+    #[serde(rename = "line_count")]
+    pub line_count2: i64,
+    pub options: OrderPayment,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum OrderPayment2 {
+    Card(Card),
+    Voucher(Voucher),
+    Long(i64),
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum OrderRefund {
+    Card(Card),
+    String(String),
+}
+
+/// Fixed of 4 bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Id(#[serde(with = "serde_bytes")] pub Vec<u8>);
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Card {
+    pub number: String,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Voucher {
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum State {
+    #[serde(rename = "in_stock")]
+    InStock,
+    #[serde(rename = "IN_STOCK")]
+    InStock2,
+    #[serde(rename = "shipped")]
+    Shipped,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OrderPayment {
+    pub r#type: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Address {
+    pub street: String,
+    pub zip: Option<String>,
+}
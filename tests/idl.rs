@@ -0,0 +1,185 @@
+use std::{fs, path::PathBuf};
+
+use avro_poc::{idl, Name, SchemaError, SerializerConfig, Value};
+use serde_json::json;
+
+#[test]
+fn protocol_declares_named_types_and_messages() {
+    let protocol = idl::parse(
+        r#"
+        /** Shop protocol. */
+        @namespace("com.shop")
+        @version("1")
+        protocol Shop {
+            enum Status { OPEN, CLOSED } = OPEN;
+            fixed Hash(4);
+            /** An order. */
+            record Order {
+                long id;
+                string? note = null;
+                array<Status> history = [];
+                @logicalType("timestamp-millis") long created;
+            }
+            error NotFound { string message; }
+            Order get(long id) throws NotFound;
+            void ping() oneway;
+        }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        protocol.name,
+        Some(Name {
+            name: "Shop".into(),
+            namespace: Some("com.shop".into()),
+        })
+    );
+    assert_eq!(protocol.doc.as_deref(), Some("Shop protocol."));
+    assert_eq!(protocol.attributes["version"], "1");
+    assert_eq!(
+        protocol.types[2],
+        json!({
+            "type": "record",
+            "name": "Order",
+            "namespace": "com.shop",
+            "doc": "An order.",
+            "fields": [
+                {"name": "id", "type": "long"},
+                {"name": "note", "type": ["null", "string"], "default": null},
+                {"name": "history", "type": {"type": "array", "items": "com.shop.Status"}, "default": []},
+                {"name": "created", "type": {"type": "long", "logicalType": "timestamp-millis"}}
+            ]
+        })
+    );
+    assert_eq!(protocol.types[0]["default"], "OPEN");
+    assert_eq!(protocol.types[1]["size"], 4);
+    assert_eq!(
+        protocol.messages["get"],
+        json!({
+            "request": [{"name": "id", "type": "long"}],
+            "response": "com.shop.Order",
+            "errors": ["com.shop.NotFound"]
+        })
+    );
+    assert_eq!(protocol.messages["ping"]["one-way"], true);
+    assert_eq!(protocol.to_json()["protocol"], "Shop");
+
+    let serializer = protocol
+        .serializer(Some("Order"), SerializerConfig::default())
+        .unwrap();
+    assert_eq!(
+        serializer
+            .serialize(&Value::Record(vec![
+                ("id".into(), Value::Long(1)),
+                ("note".into(), Value::Union(0, Box::new(Value::Null))),
+                ("history".into(), Value::Array(vec![])),
+                ("created".into(), Value::Long(2)),
+            ]))
+            .unwrap(),
+        [2, 0, 0, 4]
+    );
+}
+
+#[test]
+fn schema_file_declares_main_schema() {
+    let protocol = idl::parse(
+        "namespace com.shop;
+        schema array<Item>;
+        record Item { string name; }",
+    )
+    .unwrap();
+    assert!(protocol.name.is_none());
+    assert_eq!(
+        protocol.schema,
+        Some(json!({"type": "array", "items": "com.shop.Item"}))
+    );
+    let serializer = protocol
+        .serializer(None, SerializerConfig::default())
+        .unwrap();
+    assert_eq!(serializer.serialize(&Vec::<()>::new()).unwrap(), [0]);
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("avro_poc_{name}_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn imports_are_relative_to_the_file_and_read_once() {
+    let dir = temp_dir("imports");
+    fs::write(
+        dir.join("common.avdl"),
+        r#"@namespace("com.common") protocol Common { record Id { long value; } }"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("money.avsc"),
+        r#"{"type": "fixed", "name": "Money", "size": 8}"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("events.avpr"),
+        r#"{"protocol": "Events", "namespace": "com.events",
+            "types": [{"type": "enum", "name": "Kind", "symbols": ["A"]}],
+            "messages": {"notify": {"request": [], "response": "null"}}}"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("main.avdl"),
+        r#"protocol Main {
+            import idl "common.avdl";
+            import idl "common.avdl";
+            import schema "money.avsc";
+            import protocol "events.avpr";
+            record Payment { com.common.Id id; Money amount; com.events.Kind kind; }
+        }"#,
+    )
+    .unwrap();
+    let protocol = idl::parse_file(dir.join("main.avdl")).unwrap();
+    let names = protocol
+        .types
+        .iter()
+        .map(|schema| schema["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Id", "Money", "Kind", "Payment"]);
+    assert_eq!(protocol.types[2]["namespace"], "com.events");
+    assert!(protocol.messages.contains_key("notify"));
+    assert!(protocol
+        .serializer(Some("Payment"), SerializerConfig::default())
+        .is_ok());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+fn error(input: &str) -> String {
+    match idl::parse(input) {
+        Err(SchemaError::Invalid { error, .. }) => error,
+        result => panic!("expected invalid IDL, found {result:?}"),
+    }
+}
+
+#[test]
+fn malformed_input_reports_the_line() {
+    assert_eq!(
+        error("protocol P {\n  record R { int x }\n}"),
+        "line 2: expected ';'"
+    );
+    assert_eq!(
+        error("protocol P {\n/* open"),
+        "line 2: unterminated comment"
+    );
+    assert_eq!(
+        error("protocol P { import foo \"x\"; }"),
+        "line 1: unknown import kind foo"
+    );
+    assert_eq!(error("int x;"), "line 1: expected named type declaration");
+    assert_eq!(
+        error("protocol P {}\nrecord"),
+        "line 2: unexpected content after protocol"
+    );
+    assert_eq!(
+        error("protocol P { fixed F(\"4\"); }"),
+        "line 1: expected fixed size"
+    );
+    assert!(idl::parse("protocol P { import idl \"missing.avdl\"; }").is_err());
+}
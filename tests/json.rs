@@ -0,0 +1,192 @@
+use std::fmt;
+
+use avro_poc::{SerializationError, Serializer};
+use serde::{de, ser::SerializeStruct, Deserialize, Deserializer, Serialize};
+
+/// Bytes serialized with `serialize_bytes`, as `serde_bytes` does.
+#[derive(Debug, PartialEq)]
+struct Bytes(Vec<u8>);
+
+impl Serialize for Bytes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BytesVisitor;
+
+        impl de::Visitor<'_> for BytesVisitor {
+            type Value = Bytes;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("bytes")
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Bytes, E> {
+                Ok(Bytes(v.to_vec()))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+fn round_trip<T>(schema: &str, value: &T, json: &str)
+where
+    T: Serialize + for<'de> Deserialize<'de> + PartialEq + fmt::Debug,
+{
+    let serializer = Serializer::parse_str(schema).unwrap();
+    assert_eq!(serializer.serialize_json(value).unwrap(), json);
+    assert_eq!(serializer.deserialize_json::<T>(json).unwrap(), *value);
+    let mut binary = Vec::new();
+    serializer
+        .json_to_binary(json.as_bytes(), &mut binary)
+        .unwrap();
+    assert_eq!(binary, serializer.serialize(value).unwrap());
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Cat {
+    lives: i32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Dog {
+    name: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Pet {
+    Cat(Cat),
+    Dog(Dog),
+}
+
+#[test]
+fn unions_are_wrapped_in_their_branch_name() {
+    let schema = r#"[
+        {"type": "record", "name": "Cat", "fields": [{"name": "lives", "type": "int"}]},
+        {"type": "record", "name": "Dog", "namespace": "pets", "fields": [
+            {"name": "name", "type": ["null", "string"]}
+        ]}
+    ]"#;
+    round_trip(
+        schema,
+        &Pet::Cat(Cat { lives: 9 }),
+        r#"{"Cat":{"lives":9}}"#,
+    );
+    round_trip(
+        schema,
+        &Pet::Dog(Dog {
+            name: Some("Rex".into()),
+        }),
+        r#"{"pets.Dog":{"name":{"string":"Rex"}}}"#,
+    );
+    round_trip(
+        schema,
+        &Pet::Dog(Dog { name: None }),
+        r#"{"pets.Dog":{"name":null}}"#,
+    );
+}
+
+#[test]
+fn bytes_and_fixed_are_escaped_code_points() {
+    let bytes = Bytes(vec![0, b'"', b'\\', 0x7f, 0xff]);
+    let json = "\"\\u0000\\\"\\\\\u{7f}\u{ff}\"";
+    round_trip(r#""bytes""#, &bytes, json);
+    round_trip(r#"{"type": "fixed", "name": "F", "size": 5}"#, &bytes, json);
+    round_trip(
+        r#"["null", {"type": "fixed", "name": "F", "size": 5}]"#,
+        &Some(bytes),
+        &format!(r#"{{"F":{json}}}"#),
+    );
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Event {
+    id: String,
+    day: String,
+    at: String,
+    price: String,
+    count: i64,
+}
+
+#[test]
+fn logical_types_are_read_as_strings() {
+    round_trip(
+        r#"{"type": "record", "name": "Event", "fields": [
+            {"name": "id", "type": {"type": "string", "logicalType": "uuid"}},
+            {"name": "day", "type": {"type": "int", "logicalType": "date"}},
+            {"name": "at", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+            {"name": "price", "type": {"type": "bytes", "logicalType": "decimal", "precision": 4, "scale": 2}},
+            {"name": "count", "type": {"type": "long", "logicalType": "timestamp-micros"}}
+        ]}"#,
+        &Event {
+            id: "67e55044-10b1-426f-9247-bb680e5fe0c8".into(),
+            day: "1970-01-02".into(),
+            at: "1970-01-01T00:00:01.500Z".into(),
+            price: "-12.34".into(),
+            count: 42,
+        },
+        r#"{"at":1500,"count":42,"day":1,"id":"67e55044-10b1-426f-9247-bb680e5fe0c8","price":"û."}"#,
+    );
+}
+
+/// Fields declared in another order than in the schema.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Reordered {
+    c: i32,
+    a: i32,
+    b: String,
+}
+
+/// A struct serializing one of its fields twice.
+struct Twice;
+
+impl Serialize for Twice {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut record = serializer.serialize_struct("Twice", 2)?;
+        record.serialize_field("a", &1)?;
+        record.serialize_field("a", &2)?;
+        record.end()
+    }
+}
+
+#[test]
+fn fields_may_be_serialized_out_of_order() {
+    let schema = r#"{"type": "record", "name": "Reordered", "fields": [
+        {"name": "a", "type": "int"},
+        {"name": "b", "type": "string"},
+        {"name": "c", "type": "int"}
+    ]}"#;
+    let value = Reordered {
+        c: 3,
+        a: 1,
+        b: "x".into(),
+    };
+    round_trip(schema, &value, r#"{"a":1,"b":"x","c":3}"#);
+    let serializer = Serializer::parse_str(schema).unwrap();
+    let error = serializer.serialize_json(&Twice).unwrap_err();
+    assert!(error.to_string().contains("duplicate field a"), "{error}");
+}
+
+#[test]
+fn null_is_none_only_in_unions_with_a_null_branch() {
+    let nullable = Serializer::parse_str(r#"["null", "string", "int"]"#).unwrap();
+    assert_eq!(
+        nullable.deserialize_json::<Option<String>>("null").unwrap(),
+        None
+    );
+    let serializer = Serializer::parse_str(r#"["string", "int"]"#).unwrap();
+    assert!(matches!(
+        serializer.deserialize_json::<Option<String>>("null"),
+        Err(SerializationError::SchemaMismatch { .. })
+    ));
+    assert_eq!(
+        serializer
+            .deserialize_json::<Option<String>>(r#"{"string":"a"}"#)
+            .unwrap(),
+        Some("a".into())
+    );
+}
@@ -0,0 +1,117 @@
+use avro_poc::{Fingerprint, SchemaError, Serializer, SerializerConfig, Symbols, Value};
+
+const SPAN: &str = r#"{"type": "record", "name": "Span", "fields": [
+    {"name": "a", "type": {"type": "fixed", "name": "Interval", "size": 12, "logicalType": "duration"}},
+    {"name": "b", "type": "Interval"}
+]}"#;
+
+#[test]
+fn duration_keeps_the_name_of_its_fixed() {
+    let serializer = Serializer::parse_str(SPAN).unwrap();
+    assert_eq!(
+        serializer.schema().canonical_form(),
+        r#"{"name":"Span","type":"record","fields":[{"name":"a","type":{"name":"Interval","type":"fixed","size":12}},{"name":"b","type":"Interval"}]}"#
+    );
+    let span = Value::Record(vec![
+        ("a".into(), Value::Duration(vec![1; 12].into())),
+        ("b".into(), Value::Duration(vec![2; 12].into())),
+    ]);
+    let bytes = serializer.serialize(&span).unwrap();
+    assert_eq!(serializer.decode(&bytes).unwrap(), span);
+
+    let serializer = Serializer::parse_str(
+        r#"["null", {"type": "fixed", "name": "Interval", "size": 12, "logicalType": "duration"}]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        serializer
+            .serialize_json(&Value::Duration(vec![0; 12].into()))
+            .unwrap(),
+        format!(r#"{{"Interval":"{}"}}"#, "\\u0000".repeat(12))
+    );
+}
+
+#[test]
+fn references_to_a_decimal_fixed_are_decimals() {
+    let serializer = Serializer::parse_str(
+        r#"{"type": "record", "name": "Prices", "fields": [
+            {"name": "low", "type": {"type": "fixed", "name": "Money", "size": 2, "logicalType": "decimal", "precision": 4, "scale": 2}},
+            {"name": "high", "type": "Money"}
+        ]}"#,
+    )
+    .unwrap();
+    let prices = Value::Record(vec![
+        ("low".into(), Value::Decimal(vec![0, 1].into())),
+        ("high".into(), Value::Decimal(vec![1, 0].into())),
+    ]);
+    let bytes = serializer.serialize(&prices).unwrap();
+    assert_eq!(bytes, [0, 1, 1, 0]);
+    assert_eq!(serializer.decode(&bytes).unwrap(), prices);
+}
+
+#[test]
+fn canonical_form_inlines_types_of_dependencies() {
+    let serializer = Serializer::parse_with_dependencies(
+        r#"{"type": "record", "name": "Line", "namespace": "geo", "fields": [
+            {"name": "from", "type": "Point"},
+            {"name": "to", "type": "geo.Point"}
+        ]}"#,
+        &[r#"{"type": "record", "name": "Point", "namespace": "geo", "fields": [{"name": "x", "type": "int"}]}"#],
+        SerializerConfig::default(),
+    )
+    .unwrap();
+    let canonical_form = r#"{"name":"geo.Line","type":"record","fields":[{"name":"from","type":{"name":"geo.Point","type":"record","fields":[{"name":"x","type":"int"}]}},{"name":"to","type":"geo.Point"}]}"#;
+    assert_eq!(serializer.canonical_form(), canonical_form);
+    let inline = Serializer::parse_str(canonical_form).unwrap();
+    assert_eq!(inline.canonical_form(), canonical_form);
+    assert_eq!(serializer.fingerprint(), inline.fingerprint());
+    assert_eq!(*serializer.fingerprint(), Fingerprint::new(inline.schema()));
+}
+
+#[test]
+fn fixed_size_must_be_a_positive_int() {
+    let fixed = |size: &str| {
+        Serializer::parse_str(&format!(
+            r#"{{"type": "fixed", "name": "F", "size": {size}}}"#
+        ))
+    };
+    assert!(fixed("1").is_ok());
+    assert!(fixed("2147483647").is_ok());
+    for size in ["0", "-1", "2147483648", "1.5", r#""4""#] {
+        assert!(fixed(size).is_err(), "{size}");
+    }
+}
+
+#[test]
+fn symbols_are_found_despite_hash_collisions() {
+    // Far more symbols than buckets of a single seed can hold without collisions.
+    let names = (0..2000).map(|i| format!("S{i}")).collect::<Vec<_>>();
+    let symbols = Symbols::new(names.clone()).unwrap();
+    assert_eq!(symbols.len(), 2000);
+    for (index, name) in names.iter().enumerate() {
+        assert_eq!(symbols.position(name), Some(index));
+        assert_eq!(symbols.get(index), Some(name.as_str()));
+    }
+    // Absent symbols land in slots of other symbols.
+    for i in 2000..4000 {
+        assert!(!symbols.contains(&format!("S{i}")));
+    }
+    assert!(!symbols.contains(""));
+    assert!(symbols.iter().eq(&names));
+
+    let empty = Symbols::new(Vec::new()).unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(empty.position("A"), None);
+}
+
+#[test]
+fn duplicate_symbols_are_errors() {
+    assert!(matches!(
+        Symbols::new(vec!["A".into(), "B".into(), "A".into()]),
+        Err(SchemaError::Invalid { error, .. }) if error == "duplicate enum symbol A"
+    ));
+    assert!(matches!(
+        Serializer::parse_str(r#"{"type": "enum", "name": "Suit", "symbols": ["A", "A"]}"#),
+        Err(SchemaError::Invalid { error, path }) if error == "duplicate enum symbol A" && path == ["Suit"]
+    ));
+}
@@ -0,0 +1,736 @@
+use std::collections::BTreeMap;
+
+use avro_poc::{
+    PathSegment, SerializationError, Serializer, SerializerConfig, VariantRepresentation,
+};
+
+#[derive(serde::Serialize)]
+struct Point {
+    x: i32,
+    label: String,
+}
+
+#[test]
+fn array_items_share_the_item_schema() {
+    let serializer = Serializer::parse_str(
+        r#"{"type": "array", "items": {
+            "type": "record",
+            "name": "Point",
+            "fields": [{"name": "x", "type": "int"}, {"name": "label", "type": "string"}]
+        }}"#,
+    )
+    .unwrap();
+    let points = [
+        Point {
+            x: 1,
+            label: "a".into(),
+        },
+        Point {
+            x: 2,
+            label: "b".into(),
+        },
+    ];
+    assert_eq!(
+        serializer.serialize(&points).unwrap(),
+        [4, 2, 2, b'a', 4, 2, b'b', 0]
+    );
+}
+
+#[test]
+fn map_keys_are_strings() {
+    let serializer = Serializer::parse_str(r#"{"type": "map", "values": "int"}"#).unwrap();
+    let map = BTreeMap::from([("a", 1), ("b", 2)]);
+    assert_eq!(
+        serializer.serialize(&map).unwrap(),
+        [4, 2, b'a', 2, 2, b'b', 4, 0]
+    );
+}
+
+#[derive(serde::Serialize)]
+enum Shape {
+    Circle { radius: i32 },
+    Square { side: i32 },
+}
+
+#[test]
+fn struct_variant_selects_union_branch_by_name() {
+    let serializer = Serializer::parse_str(
+        r#"[
+            {"type": "record", "name": "Circle", "fields": [{"name": "radius", "type": "int"}]},
+            {"type": "record", "name": "Square", "fields": [{"name": "side", "type": "int"}]}
+        ]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        serializer.serialize(&Shape::Square { side: 3 }).unwrap(),
+        [2, 6]
+    );
+    assert_eq!(
+        serializer.serialize(&Shape::Circle { radius: 1 }).unwrap(),
+        [0, 2]
+    );
+}
+
+#[derive(serde::Serialize)]
+enum Color {
+    Red,
+    Purple,
+    #[serde(rename = "null")]
+    Unknown,
+}
+
+#[test]
+fn unit_variant_selects_enum_symbol_or_null_branch() {
+    let serializer = Serializer::parse_str(
+        r#"["null", {"type": "enum", "name": "Color", "symbols": ["Green", "Red"]}]"#,
+    )
+    .unwrap();
+    assert_eq!(serializer.serialize(&Color::Red).unwrap(), [2, 2]);
+    assert_eq!(serializer.serialize(&Color::Unknown).unwrap(), [0]);
+    assert!(serializer.serialize(&Color::Purple).is_err());
+    assert!(serializer.serialize_json(&Color::Purple).is_err());
+
+    let serializer = Serializer::parse_str(r#"["string", "null"]"#).unwrap();
+    assert_eq!(serializer.serialize(&Color::Purple).unwrap(), [2]);
+}
+
+#[derive(serde::Serialize)]
+struct Cat {
+    lives: i32,
+}
+
+#[derive(serde::Serialize)]
+struct Dog {
+    good: bool,
+}
+
+#[derive(serde::Serialize)]
+struct Bird {
+    wings: i32,
+}
+
+const CAT: &str =
+    r#"{"type": "record", "name": "Cat", "fields": [{"name": "lives", "type": "int"}]}"#;
+const DOG: &str =
+    r#"{"type": "record", "name": "Dog", "fields": [{"name": "good", "type": "boolean"}]}"#;
+
+#[test]
+fn struct_selects_union_record_branch_by_name() {
+    let serializer = Serializer::parse_str(&format!("[{CAT}, {DOG}]")).unwrap();
+    assert_eq!(serializer.serialize(&Dog { good: true }).unwrap(), [2, 1]);
+    assert_eq!(serializer.serialize(&Cat { lives: 9 }).unwrap(), [0, 18]);
+    assert!(serializer.serialize(&Bird { wings: 2 }).is_err());
+    assert_eq!(
+        serializer.serialize_json(&Dog { good: true }).unwrap(),
+        r#"{"Dog":{"good":true}}"#
+    );
+
+    let serializer = Serializer::parse_with_dependencies(
+        r#"["null", "Cat", "Dog"]"#,
+        &[CAT, DOG],
+        SerializerConfig::default(),
+    )
+    .unwrap();
+    assert_eq!(serializer.serialize(&Dog { good: true }).unwrap(), [4, 1]);
+    assert_eq!(serializer.serialize(&Cat { lives: 9 }).unwrap(), [2, 18]);
+}
+
+#[test]
+fn union_branches_have_distinct_types_or_names() {
+    assert!(Serializer::parse_str(r#"["int", "string", "int"]"#).is_err());
+    assert!(Serializer::parse_str(&format!("[{CAT}, {CAT}]")).is_err());
+}
+
+#[derive(serde::Serialize)]
+enum Event {
+    Started,
+    Stopped { code: i32 },
+}
+
+#[test]
+fn internally_tagged_variants_write_tag_record() {
+    let config = SerializerConfig {
+        variant_representation: VariantRepresentation::InternallyTagged { tag: "kind".into() },
+    };
+    let serializer = Serializer::parse_with_dependencies(
+        r#"{"type": "record", "name": "Event", "fields": [{"name": "kind", "type": "string"}]}"#,
+        &[],
+        config.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        serializer.serialize(&Event::Started).unwrap(),
+        b"\x0eStarted"
+    );
+    assert_eq!(
+        serializer.serialize_json(&Event::Started).unwrap(),
+        r#"{"kind":"Started"}"#
+    );
+
+    let serializer = Serializer::parse_with_dependencies(
+        r#"{"type": "record", "name": "Event", "fields": [
+            {"name": "kind", "type": "string"},
+            {"name": "code", "type": "int"}
+        ]}"#,
+        &[],
+        config,
+    )
+    .unwrap();
+    assert_eq!(
+        serializer.serialize(&Event::Stopped { code: 3 }).unwrap(),
+        b"\x0eStopped\x06"
+    );
+    assert!(serializer.serialize(&Event::Started).is_err());
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn serialize_into_buf_leaves_the_buffer_unchanged_on_error() {
+    use avro_poc::SerializationError;
+
+    let serializer = Serializer::parse_str(r#"{"type": "array", "items": "int"}"#).unwrap();
+    let mut buf = bytes::BytesMut::from(&b"x"[..]);
+    serializer.serialize_into_buf(&[1, 2], &mut buf).unwrap();
+    assert_eq!(buf, &b"x\x04\x02\x04\x00"[..]);
+    assert!(serializer.serialize_into_buf(&(1, "a"), &mut buf).is_err());
+    assert_eq!(buf, &b"x\x04\x02\x04\x00"[..]);
+
+    let mut array = [0; 3];
+    let mut slice = &mut array[..];
+    assert!(matches!(
+        serializer.serialize_into_buf(&[1, 2], &mut slice),
+        Err(SerializationError::BufferTooSmall { len: 3, needed: 4 })
+    ));
+    assert_eq!(array, [0; 3]);
+}
+
+/// Serializes its keys in this order, the value of each key being its position.
+struct Keys(&'static [&'static str]);
+
+impl serde::Serialize for Keys {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut record = serializer.serialize_struct("Keys", self.0.len())?;
+        for (position, key) in self.0.iter().enumerate() {
+            record.serialize_field(key, &(position as i32))?;
+        }
+        record.end()
+    }
+}
+
+const ABC: &str = r#"{"type": "record", "name": "Abc", "fields": [
+    {"name": "a", "type": "int"},
+    {"name": "b", "type": "int"},
+    {"name": "c", "type": "int"}
+]}"#;
+
+#[test]
+fn fields_are_written_in_schema_order() {
+    let serializer = Serializer::parse_str(ABC).unwrap();
+    // Serialized twice, the second time with the field plan cached on the first.
+    for _ in 0..2 {
+        assert_eq!(
+            serializer.serialize(&Keys(&["c", "a", "b"])).unwrap(),
+            [2, 4, 0]
+        );
+    }
+    // Keys not following the plan.
+    assert_eq!(
+        serializer.serialize(&Keys(&["c", "b", "a"])).unwrap(),
+        [4, 2, 0]
+    );
+    assert_eq!(
+        serializer.serialize(&Keys(&["a", "b", "c"])).unwrap(),
+        [0, 2, 4]
+    );
+    assert_eq!(
+        serializer.serialize(&Keys(&["c", "a", "b"])).unwrap(),
+        [2, 4, 0]
+    );
+}
+
+#[derive(serde::Serialize)]
+struct Outer {
+    inner: Keys,
+    a: i32,
+}
+
+#[test]
+fn nested_fields_are_written_in_schema_order() {
+    let serializer = Serializer::parse_str(&format!(
+        r#"{{"type": "record", "name": "Outer", "fields": [
+            {{"name": "a", "type": "int"}},
+            {{"name": "inner", "type": {ABC}}}
+        ]}}"#
+    ))
+    .unwrap();
+    for _ in 0..2 {
+        let outer = Outer {
+            inner: Keys(&["c", "a", "b"]),
+            a: 7,
+        };
+        assert_eq!(serializer.serialize(&outer).unwrap(), [14, 2, 4, 0]);
+    }
+}
+
+#[test]
+fn duplicate_and_skipped_fields_are_errors() {
+    let serializer = Serializer::parse_str(ABC).unwrap();
+    for _ in 0..2 {
+        assert!(serializer.serialize(&Keys(&["c", "a", "b"])).is_ok());
+        assert!(serializer.serialize(&Keys(&["c", "a", "a"])).is_err());
+        assert!(serializer.serialize(&Keys(&["c", "c", "a", "b"])).is_err());
+        assert!(serializer.serialize(&Keys(&["a", "a", "b", "c"])).is_err());
+        assert!(serializer.serialize(&Keys(&["c", "a"])).is_err());
+        assert!(serializer.serialize(&Keys(&["a", "b"])).is_err());
+        assert!(serializer.serialize(&Keys(&["c", "a", "b", "d"])).is_err());
+    }
+    let mut buf = Vec::new();
+    assert!(serializer
+        .serialize_into(&Keys(&["c", "a"]), &mut buf)
+        .is_err());
+    assert!(buf.is_empty());
+}
+
+#[derive(serde::Serialize)]
+struct Ordered {
+    a: i32,
+    b: String,
+    c: i64,
+}
+
+#[derive(serde::Serialize)]
+struct Reversed {
+    c: i64,
+    b: String,
+    a: i32,
+}
+
+#[derive(serde::Serialize)]
+struct Skipping {
+    b: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    a: Option<i32>,
+    c: i64,
+}
+
+#[test]
+fn types_sharing_a_record_keep_their_field_order() {
+    let serializer = Serializer::parse_str(
+        r#"{"type": "record", "name": "Abc", "fields": [
+            {"name": "a", "type": "int"},
+            {"name": "b", "type": "string"},
+            {"name": "c", "type": "long"}
+        ]}"#,
+    )
+    .unwrap();
+    let expected = [2, 2, b'x', 4];
+    for _ in 0..2 {
+        let ordered = Ordered {
+            a: 1,
+            b: "x".into(),
+            c: 2,
+        };
+        assert_eq!(serializer.serialize(&ordered).unwrap(), expected);
+        let reversed = Reversed {
+            c: 2,
+            b: "x".into(),
+            a: 1,
+        };
+        assert_eq!(serializer.serialize(&reversed).unwrap(), expected);
+        let skipping = Skipping {
+            b: "x".into(),
+            a: Some(1),
+            c: 2,
+        };
+        assert_eq!(serializer.serialize(&skipping).unwrap(), expected);
+        let skipped = Skipping {
+            b: "x".into(),
+            a: None,
+            c: 2,
+        };
+        assert!(serializer.serialize(&skipped).is_err());
+    }
+}
+
+#[test]
+fn serializers_are_shared_across_threads() {
+    let serializer = Serializer::parse_str(ABC).unwrap();
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            let serializer = serializer.clone();
+            scope.spawn(move || {
+                // Parsed again on each thread, the schema is taken from the cache.
+                let parsed = Serializer::parse_str(ABC).unwrap();
+                for _ in 0..100 {
+                    for serializer in [&serializer, &parsed] {
+                        assert_eq!(
+                            serializer.serialize(&Keys(&["c", "a", "b"])).unwrap(),
+                            [2, 4, 0]
+                        );
+                    }
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn cached_schemas_keep_their_logical_types_and_config() {
+    let int = Serializer::parse_str(r#""int""#).unwrap();
+    let date = Serializer::parse_str(r#"{"type": "int", "logicalType": "date"}"#).unwrap();
+    assert_eq!(int.fingerprint(), date.fingerprint());
+    assert!(int.serialize(&"1970-01-02").is_err());
+    assert_eq!(date.serialize(&"1970-01-02").unwrap(), [2]);
+
+    let event =
+        r#"{"type": "record", "name": "Event", "fields": [{"name": "kind", "type": "string"}]}"#;
+    let union = Serializer::parse_with_dependencies(event, &[], SerializerConfig::default());
+    assert!(union.unwrap().serialize(&Event::Started).is_err());
+    let config = SerializerConfig {
+        variant_representation: VariantRepresentation::InternallyTagged { tag: "kind".into() },
+    };
+    let tagged = Serializer::parse_with_dependencies(event, &[], config).unwrap();
+    assert_eq!(tagged.serialize(&Event::Started).unwrap(), b"\x0eStarted");
+}
+
+#[test]
+fn options_select_the_other_branch_of_nullable_unions() {
+    for (schema, null, string) in [
+        (r#"["null", "string"]"#, 0, 2),
+        (r#"["string", "null"]"#, 2, 0),
+    ] {
+        let serializer = Serializer::parse_str(schema).unwrap();
+        assert_eq!(serializer.serialize(&None::<&str>).unwrap(), [null]);
+        assert_eq!(serializer.serialize(&Some("a")).unwrap(), [string, 2, b'a']);
+        assert_eq!(serializer.serialize_json(&None::<&str>).unwrap(), "null");
+        assert_eq!(
+            serializer.serialize_json(&Some("a")).unwrap(),
+            r#"{"string":"a"}"#
+        );
+        // The inner value is serialized in the non-null branch, so it can't be null itself, in
+        // binary as in JSON.
+        assert!(serializer.serialize(&Some(())).is_err());
+        assert!(serializer.serialize(&Some(None::<&str>)).is_err());
+        assert!(serializer.serialize_json(&Some(())).is_err());
+        assert!(serializer.serialize_json(&Some(None::<&str>)).is_err());
+        assert_eq!(serializer.serialize(&()).unwrap(), [null]);
+        assert_eq!(serializer.serialize_json(&()).unwrap(), "null");
+    }
+    // Options of other unions are serialized as their value, so a null value selects the null
+    // branch.
+    let serializer = Serializer::parse_str(r#"["int", "null", "string"]"#).unwrap();
+    for value in [None, Some(None), Some(Some(()))] {
+        assert_eq!(serializer.serialize(&value).unwrap(), [2]);
+        assert_eq!(serializer.serialize_json(&value).unwrap(), "null");
+    }
+    assert_eq!(serializer.serialize(&Some("a")).unwrap(), [4, 2, b'a']);
+    assert_eq!(
+        serializer.serialize_json(&Some("a")).unwrap(),
+        r#"{"string":"a"}"#
+    );
+}
+
+#[derive(serde::Serialize)]
+struct Log {
+    entries: Vec<Option<Entry>>,
+    tags: BTreeMap<&'static str, &'static str>,
+}
+
+#[derive(serde::Serialize)]
+struct Entry {
+    level: &'static str,
+    message: i32,
+}
+
+const LOG: &str = r#"{"type": "record", "name": "Log", "fields": [
+    {"name": "entries", "type": {"type": "array", "items": ["null", {
+        "type": "record",
+        "name": "Entry",
+        "fields": [
+            {"name": "level", "type": {"type": "enum", "name": "Level", "symbols": ["INFO", "WARN"]}},
+            {"name": "message", "type": "string"}
+        ]
+    }]}},
+    {"name": "tags", "type": {"type": "map", "values": "int"}}
+]}"#;
+
+/// A log with an unknown level, an int message and a string tag.
+fn invalid_log() -> Log {
+    Log {
+        entries: vec![
+            None,
+            Some(Entry {
+                level: "DEBUG",
+                message: 1,
+            }),
+        ],
+        tags: BTreeMap::from([("a", "x")]),
+    }
+}
+
+#[test]
+fn validation_stops_at_the_first_error_or_reports_every_error() {
+    let serializer = Serializer::parse_str(LOG).unwrap();
+    let valid = Log {
+        entries: vec![None],
+        tags: BTreeMap::new(),
+    };
+    assert!(serializer.validate(&valid).is_ok());
+    assert!(serializer.validate_all(&valid).is_ok());
+    let invalid = invalid_log();
+    assert_eq!(
+        serializer.validate(&invalid).unwrap_err().to_string(),
+        serializer.serialize(&invalid).unwrap_err().to_string()
+    );
+    assert_eq!(serializer.validate_all(&invalid).unwrap_err().len(), 3);
+}
+
+fn path(error: &SerializationError) -> Vec<PathSegment> {
+    match error {
+        SerializationError::SchemaMismatch { path, .. }
+        | SerializationError::Custom { path, .. } => path.iter().cloned().collect(),
+        error => panic!("expected an error with a path, found {error:?}"),
+    }
+}
+
+#[test]
+fn errors_collected_in_nested_values_keep_their_full_path() {
+    let serializer = Serializer::parse_str(LOG).unwrap();
+    // The errors of the entry are collected together, then prefixed with the path of the entry.
+    let errors = serializer.validate_all(&invalid_log()).unwrap_err();
+    let entry = [
+        PathSegment::Type("Log"),
+        PathSegment::Field("entries"),
+        PathSegment::Index(1),
+        PathSegment::Branch(1),
+        PathSegment::Type("Entry"),
+    ];
+    assert_eq!(
+        errors.iter().map(path).collect::<Vec<_>>(),
+        [
+            [&entry[..], &[PathSegment::Field("level")]].concat(),
+            [&entry[..], &[PathSegment::Field("message")]].concat(),
+            vec![
+                PathSegment::Type("Log"),
+                PathSegment::Field("tags"),
+                PathSegment::Key("a".into()),
+            ],
+        ]
+    );
+    assert_eq!(
+        errors[1].to_string(),
+        "expected String, found Int (path: Log.entries[1]<1>.Entry.message)"
+    );
+    assert_eq!(
+        SerializationError::Multiple(errors).to_string(),
+        "3 errors, first: unexpected DEBUG in enum (path: Log.entries[1]<1>.Entry.level)"
+    );
+}
+
+#[derive(serde::Serialize)]
+struct Profile {
+    id: i64,
+    name: String,
+    scores: Vec<f64>,
+    counts: BTreeMap<String, i32>,
+    parent: Option<Box<Profile>>,
+}
+
+#[test]
+fn serialized_size_is_the_length_of_the_encoding() {
+    let serializer = Serializer::parse_str(
+        r#"{"type": "record", "name": "Profile", "fields": [
+            {"name": "id", "type": "long"},
+            {"name": "name", "type": "string"},
+            {"name": "scores", "type": {"type": "array", "items": "double"}},
+            {"name": "counts", "type": {"type": "map", "values": "int"}},
+            {"name": "parent", "type": ["null", "Profile"]}
+        ]}"#,
+    )
+    .unwrap();
+    let leaf = Profile {
+        id: 0,
+        name: String::new(),
+        scores: Vec::new(),
+        counts: BTreeMap::new(),
+        parent: None,
+    };
+    let value = Profile {
+        id: i64::MIN,
+        name: "é".repeat(100),
+        scores: vec![1.5; 70],
+        counts: (0..100).map(|i| (i.to_string(), i * 1000)).collect(),
+        parent: Some(Box::new(leaf)),
+    };
+    for value in [&value, value.parent.as_deref().unwrap()] {
+        assert_eq!(
+            serializer.serialized_size(value).unwrap(),
+            serializer.serialize(value).unwrap().len()
+        );
+    }
+    // Fields written out of order are buffered, but counted once.
+    let serializer = Serializer::parse_str(ABC).unwrap();
+    let keys = Keys(&["c", "a", "b"]);
+    assert_eq!(serializer.serialized_size(&keys).unwrap(), 3);
+    assert!(serializer.serialized_size(&Keys(&["a", "b"])).is_err());
+}
+
+#[test]
+fn batches_hold_the_encoding_of_each_value() {
+    let serializer = Serializer::parse_str(ABC).unwrap();
+    let values = [Keys(&["a", "b", "c"]), Keys(&["c", "a", "b"])];
+    let mut batch = serializer.serialize_batch(&values).unwrap();
+    let encodings = [[0, 2, 4], [2, 4, 0]];
+    assert_eq!(batch.len(), 2);
+    assert_eq!(batch.iter().collect::<Vec<_>>(), encodings);
+    assert_eq!(batch.buffer(), encodings.concat());
+    assert_eq!(batch.ends(), [3, 6]);
+    assert_eq!(batch.get(1), Some(&[2, 4, 0][..]));
+    assert_eq!(batch.get(2), None);
+
+    // A failing value leaves the batch unchanged, and its index starts the error path.
+    let before = batch.clone();
+    let error = serializer
+        .serialize_batch_into([Keys(&["b", "c", "a"]), Keys(&["a", "b"])], &mut batch)
+        .unwrap_err();
+    assert_eq!(path(&error)[0], PathSegment::Index(1));
+    assert_eq!(batch, before);
+    serializer
+        .serialize_batch_into([Keys(&["b", "c", "a"])], &mut batch)
+        .unwrap();
+    assert_eq!(batch.len(), 3);
+    assert_eq!(batch.get(2), Some(&[4, 0, 2][..]));
+
+    batch.clear();
+    assert!(batch.is_empty());
+    serializer
+        .serialize_batch_into(&values, &mut batch)
+        .unwrap();
+    assert_eq!(batch.iter().collect::<Vec<_>>(), encodings);
+    #[cfg(feature = "bytes")]
+    assert_eq!(
+        batch.into_bytes(),
+        encodings.map(|encoding| encoding.to_vec())
+    );
+}
+
+/// Variants in another order than the schema symbols.
+#[derive(serde::Serialize)]
+enum Level {
+    Warn,
+    Info,
+    Error,
+}
+
+#[test]
+fn unit_variants_are_looked_up_when_not_at_their_symbol_index() {
+    let serializer =
+        Serializer::parse_str(r#"{"type": "enum", "name": "Level", "symbols": ["Info", "Warn"]}"#)
+            .unwrap();
+    assert_eq!(serializer.serialize(&Level::Info).unwrap(), [0]);
+    assert_eq!(serializer.serialize(&Level::Warn).unwrap(), [2]);
+    assert!(serializer.serialize(&Level::Error).is_err());
+}
+
+/// Writer accepting at most `limit` bytes per call, vectored or not, and interrupted once.
+struct ShortWriter {
+    written: Vec<u8>,
+    limit: usize,
+    calls: usize,
+    interrupted: bool,
+}
+
+impl ShortWriter {
+    fn new(limit: usize) -> Self {
+        Self {
+            written: Vec::new(),
+            limit,
+            calls: 0,
+            interrupted: false,
+        }
+    }
+}
+
+impl std::io::Write for ShortWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.write_vectored(&[std::io::IoSlice::new(buf)])
+    }
+
+    fn write_vectored(&mut self, bufs: &[std::io::IoSlice<'_>]) -> std::io::Result<usize> {
+        if !self.interrupted {
+            self.interrupted = true;
+            return Err(std::io::ErrorKind::Interrupted.into());
+        }
+        self.calls += 1;
+        let start = self.written.len();
+        for buf in bufs {
+            let remaining = self.limit - (self.written.len() - start);
+            self.written
+                .extend_from_slice(&buf[..buf.len().min(remaining)]);
+        }
+        Ok(self.written.len() - start)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Payload {
+    ids: Vec<i64>,
+    #[serde(with = "serde_bytes")]
+    blob: Vec<u8>,
+    tail: String,
+}
+
+#[test]
+fn buffered_writes_cross_the_buffer_capacity_and_survive_short_writes() {
+    let serializer = Serializer::parse_str(
+        r#"{"type": "record", "name": "Payload", "fields": [
+            {"name": "ids", "type": {"type": "array", "items": "long"}},
+            {"name": "blob", "type": "bytes"},
+            {"name": "tail", "type": "string"}
+        ]}"#,
+    )
+    .unwrap();
+    // About 15 KiB of varints, then a payload larger than the 8 KiB buffer.
+    let payload = Payload {
+        ids: (0..5000).map(|i| i * 1000).collect(),
+        blob: (0..20_000).map(|i| i as u8).collect(),
+        tail: "end".into(),
+    };
+    let expected = serializer.serialize(&payload).unwrap();
+    for limit in [usize::MAX, 8 * 1024 - 1, 1000, 7] {
+        let mut writer = ShortWriter::new(limit);
+        serializer.write(&payload, &mut writer).unwrap();
+        assert_eq!(writer.written, expected, "{limit}");
+        if limit == usize::MAX {
+            // The varints are batched, the blob written along with the buffered bytes.
+            assert!(writer.calls <= 4, "{} calls", writer.calls);
+        }
+    }
+    let mut writer = ShortWriter::new(0);
+    assert!(matches!(
+        serializer.write(&payload, &mut writer),
+        Err(SerializationError::Io(error)) if error.kind() == std::io::ErrorKind::WriteZero
+    ));
+}
+
+#[test]
+fn serializers_outlive_their_eviction_from_the_cache() {
+    let schema = |i: usize| format!(r#"{{"type": "fixed", "name": "F{i}", "size": 1}}"#);
+    let byte = serde_bytes::ByteBuf::from([7]);
+    let first = Serializer::parse_str(&schema(0)).unwrap();
+    // More schemas than the cache holds, evicting the first ones.
+    let serializers = (1..300)
+        .map(|i| Serializer::parse_str(&schema(i)).unwrap())
+        .collect::<Vec<_>>();
+    for serializer in [&first].into_iter().chain(&serializers) {
+        assert_eq!(serializer.serialize(&byte).unwrap(), [7]);
+    }
+    let parsed = Serializer::parse_str(&schema(0)).unwrap();
+    assert_eq!(parsed.fingerprint(), first.fingerprint());
+    assert_eq!(parsed.serialize(&byte).unwrap(), [7]);
+}
@@ -0,0 +1,97 @@
+use avro_poc::{SerializationError, Serializer, Value};
+
+const SCHEMA: &str = r#"{"type": "record", "name": "Sample", "fields": [
+    {"name": "int", "type": "int"},
+    {"name": "long", "type": "long"},
+    {"name": "double", "type": "double"},
+    {"name": "string", "type": "string"},
+    {"name": "bytes", "type": "bytes"},
+    {"name": "fixed", "type": {"type": "fixed", "name": "Id", "size": 2}},
+    {"name": "enum", "type": {"type": "enum", "name": "Color", "symbols": ["RED", "GREEN"]}},
+    {"name": "array", "type": {"type": "array", "items": "int"}},
+    {"name": "map", "type": {"type": "map", "values": "boolean"}},
+    {"name": "option", "type": ["null", "string"]},
+    {"name": "union", "type": ["string", "Id", {"type": "record", "name": "Point", "fields": [
+        {"name": "x", "type": "int"}
+    ]}]},
+    {"name": "date", "type": {"type": "int", "logicalType": "date"}},
+    {"name": "timestamp", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+    {"name": "decimal", "type": {"type": "bytes", "logicalType": "decimal", "precision": 4, "scale": 2}},
+    {"name": "uuid", "type": {"type": "string", "logicalType": "uuid"}},
+    {"name": "duration", "type": {"type": "fixed", "name": "Span", "size": 12, "logicalType": "duration"}}
+]}"#;
+
+fn sample(union: Value<'static>) -> Value<'static> {
+    Value::Record(vec![
+        ("int".into(), Value::Int(-3)),
+        ("long".into(), Value::Long(1 << 40)),
+        ("double".into(), Value::Double(0.5)),
+        ("string".into(), Value::String("a\"b".into())),
+        ("bytes".into(), Value::Bytes(vec![0, 0x80, 0xff].into())),
+        ("fixed".into(), Value::Fixed(vec![b'\\', 1].into())),
+        ("enum".into(), Value::Enum("GREEN".into())),
+        (
+            "array".into(),
+            Value::Array(vec![Value::Int(1), Value::Int(2)]),
+        ),
+        (
+            "map".into(),
+            Value::Map(vec![("k".into(), Value::Boolean(true))]),
+        ),
+        ("option".into(), Value::Union(0, Box::new(Value::Null))),
+        ("union".into(), union),
+        ("date".into(), Value::Date(19000)),
+        ("timestamp".into(), Value::TimestampMillis(1_500)),
+        ("decimal".into(), Value::Decimal(vec![0xfb, 0x2e].into())),
+        (
+            "uuid".into(),
+            Value::Uuid("67e55044-10b1-426f-9247-bb680e5fe0c8".into()),
+        ),
+        ("duration".into(), Value::Duration(vec![7; 12].into())),
+    ])
+}
+
+#[test]
+fn binary_and_json_round_trip() {
+    let serializer = Serializer::parse_str(SCHEMA).unwrap();
+    let unions = [
+        Value::Union(0, Box::new(Value::String("s".into()))),
+        Value::Union(1, Box::new(Value::Fixed(vec![1, 2].into()))),
+        Value::Union(
+            2,
+            Box::new(Value::Record(vec![("x".into(), Value::Int(4))])),
+        ),
+    ];
+    for union in unions {
+        let value = sample(union);
+        let binary = serializer.serialize(&value).unwrap();
+        let mut json = Vec::new();
+        serializer
+            .binary_to_json(&binary, &mut serde_json::Serializer::new(&mut json))
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&json).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&serializer.serialize_json(&value).unwrap())
+                .unwrap()
+        );
+        let mut transcoded = Vec::new();
+        serializer
+            .json_to_binary(json.as_slice(), &mut transcoded)
+            .unwrap();
+        assert_eq!(transcoded, binary);
+        assert_eq!(serializer.decode(&transcoded).unwrap(), value);
+    }
+}
+
+#[test]
+fn invalid_json_keeps_its_position() {
+    let serializer = Serializer::parse_str(r#"{"type": "map", "values": "int"}"#).unwrap();
+    match serializer.json_to_binary(&b"{\n  \"a\": 1,\n}"[..], Vec::new()) {
+        Err(SerializationError::Json(error)) => assert_eq!((error.line(), error.column()), (3, 1)),
+        result => panic!("expected a JSON error, found {result:?}"),
+    }
+    assert!(matches!(
+        serializer.json_to_binary(&br#"{"a": "b"}"#[..], Vec::new()),
+        Err(SerializationError::Custom { .. } | SerializationError::SchemaMismatch { .. })
+    ));
+}